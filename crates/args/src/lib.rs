@@ -14,14 +14,37 @@ use rslog::{
 use strings::manager::StringsManager;
 
 /// List of the possible options
-const OPTION_FLAGS: [char; 7] = [
+const OPTION_FLAGS: [char; 8] = [
     'h', // help
     'l', // library
-    'p', // platform
     'o', // output
     'd', // directory
     'a', // add
     's', // speak language
+    'j', // jobs
+    'O', // optimize
+];
+
+/// List of the possible long options, written with "--" before
+const LONG_OPTION_FLAGS: [&str; 7] = [
+    "target", // target triple
+    "print", // print some compiler information
+    "libc", // link against the C library
+    "archive", // static library
+    "force", // ignore the build cache
+    "dump", // print what is understood from the source files
+    "check-division", // stop on a division by zero
+];
+
+/// Options that never have a value, so the next argument is not taken as their
+/// value : `juc -l main.ju` is a library built from "main.ju"
+const SWITCH_FLAGS: [&str; 6] = [
+    "-l",
+    "-O",
+    "--libc",
+    "--archive",
+    "--force",
+    "--check-division",
 ];
 
 pub struct Args {
@@ -93,14 +116,20 @@ impl Args {
         for (option_flag, _option_value) in &self.options {
             // Checks if the option flag is contained into `OPTIONS` too
             let mut is_valid = false;
-            for flag in OPTION_FLAGS {
-                // Index 1 is the letter
-                // "-h", index 0 is "-" and index 1 is "h"
-                // `unwrap()` is called because here is no option's flag
-                // different than this writing: "-x": no error can be raised
-                if option_flag.chars().nth(1).unwrap() == flag {
-                    is_valid = true;
-                    break; // yes it is, I stop the check
+
+            if let Some(long_flag) = option_flag.strip_prefix("--") {
+                // "--target", the long option's name is "target"
+                is_valid = LONG_OPTION_FLAGS.contains(&long_flag);
+            } else {
+                for flag in OPTION_FLAGS {
+                    // Index 1 is the letter
+                    // "-h", index 0 is "-" and index 1 is "h"
+                    // `unwrap()` is called because here is no option's flag
+                    // different than this writing: "-x": no error can be raised
+                    if option_flag.chars().nth(1).unwrap() == flag {
+                        is_valid = true;
+                        break; // yes it is, I stop the check
+                    }
                 }
             }
 
@@ -139,6 +168,11 @@ impl Args {
             if self.is_option(&arg) && self.previous_is_option {
                 key = arg;
                 self.options.insert(key.clone(), String::new());
+
+                // Nothing to wait for, the next argument is not its value
+                if SWITCH_FLAGS.contains(&key.as_str()) {
+                    self.previous_is_option = false;
+                }
                 continue;
             }
 
@@ -197,4 +231,11 @@ impl Args {
             None => {}
         }
     }
+
+    /// Same as `Args::when_flag()` but for a long option flag like "--target"
+    pub fn when_long_flag<F: FnMut(String)>(flag: &str, options: &Dict<String, String>, mut do_what: F) {
+        if let Some(value) = options.get(format!("--{}", flag).as_str()) {
+            do_what(value.to_string());
+        }
+    }
 }
@@ -0,0 +1,94 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Hashes the sources generating the code, given to the build cache as
+//! "JUC_SOURCES_HASH", with the versions of the dependencies generating the
+//! code too as "JUC_DEPENDENCIES". The cache entries written by a compiler 
+//! built from other sources are then never used
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Directories of the sources changing the generated code
+const SOURCES_DIRECTORIES: [&str; 2] = ["src", "../platform/src"];
+/// Dependencies changing the generated code : the parser and the Assembly 
+/// writer
+const DEPENDENCIES: [&str; 2] = ["jup", "x64asm"];
+/// Lock file of the workspace, with the exact version of each dependency
+const LOCK_FILE: &str = "../../Cargo.lock";
+
+fn main() {
+    let mut paths = vec![];
+    for directory in SOURCES_DIRECTORIES {
+        give_files(Path::new(directory), &mut paths);
+        println!("cargo:rerun-if-changed={}", directory);
+    }
+    // The same hash on every build of the same sources
+    paths.sort();
+
+    // FNV-1a hash, like the one of the build cache
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for path in paths {
+        let content = fs::read(&path).unwrap();
+        let bytes = path.to_string_lossy().into_owned().into_bytes();
+
+        for byte in bytes.iter().chain(&content) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    println!("cargo:rustc-env=JUC_SOURCES_HASH={:016x}", hash);
+
+    println!("cargo:rerun-if-changed={}", LOCK_FILE);
+    println!("cargo:rustc-env=JUC_DEPENDENCIES={}", give_dependencies());
+}
+
+/// Gives the locked version of each dependency changing the generated code, 
+/// with its commit when it comes from a git repository : 
+/// "+jup-0.1.0-4656ce86+x64asm-0.1.7-34fc0cb3"
+fn give_dependencies() -> String {
+    let lock = match fs::read_to_string(LOCK_FILE) {
+        Ok(lock) => lock,
+        Err(_) => return "+unlocked".to_string(),
+    };
+
+    let mut dependencies = String::new();
+
+    for package in lock.split("[[package]]") {
+        let field = |key: &str| package.lines().find_map(|line| {
+            line.strip_prefix(key)?
+                .strip_prefix(" = ")
+                .map(|value| value.trim_matches('"').to_string())
+        });
+
+        let (name, version) = match (field("name"), field("version")) {
+            (Some(name), Some(version)) if DEPENDENCIES.contains(&name.as_str()) => (name, version),
+            _ => continue,
+        };
+
+        dependencies += &format!("+{}-{}", name, version);
+
+        if let Some((_repository, commit)) = field("source").as_deref().and_then(|source| source.split_once('#')) {
+            dependencies += &format!("-{}", &commit[..commit.len().min(8)]);
+        }
+    }
+
+    dependencies
+}
+
+/// Adds to `paths` all the files in the directory and its sub-directories
+fn give_files(directory: &Path, paths: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            give_files(&path, paths);
+        } else {
+            paths.push(path);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Build cache to not compile again the source files that did not change.
+//!
+//! A cache entry is written next to the Assembly and object files of each
+//! source file, as "<source>.cache" in the output directory :
+//! ```text
+//! hash=9a3f0c51d2e8b7a4
+//! version=0.1.0-5c01e2a7b4d9f683+jup-0.1.0-4656ce86+x64asm-0.1.7-34fc0cb3
+//! flags=x86_64-linux-gnu library=false archive=false libc=false check_division=false optimized=false signatures=47c1d0e9a35b2f86
+//! export=foo
+//! ```
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::Path,
+};
+
+use crate::data::CompilerData;
+
+/// Version of the compiler with a hash of its sources and the versions of the
+/// parser and of the Assembly writer, given by the build script. A cache entry
+/// from another build of the compiler is never used
+pub const COMPILER_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"), 
+    "-", 
+    env!("JUC_SOURCES_HASH"),
+    env!("JUC_DEPENDENCIES")
+);
+
+/// Everything needed to know if a compiled source file is still up to date
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CacheEntry {
+    pub source_hash: u64,
+    pub compiler_version: String,
+    /// Options changing the generated code, see `give_flags()`
+    pub flags: String,
+    /// Symbols exported by the object file when building a library
+    pub exported_functions: Vec<String>,
+}
+
+impl CacheEntry {
+    pub fn new(source: &[u8], data: &CompilerData) -> Self {
+        Self {
+            source_hash: hash(source),
+            compiler_version: COMPILER_VERSION.to_string(),
+            flags: give_flags(data),
+            exported_functions: vec![],
+        }
+    }
+
+    /// Returns `None` when the file does not exist or is not a valid entry
+    pub fn from_file(path: &Path) -> Option<Self> {
+        Self::from_string(&fs::read_to_string(path).ok()?)
+    }
+
+    pub fn to_file(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn from_string(content: &str) -> Option<Self> {
+        let mut source_hash: Option<u64> = None;
+        let mut compiler_version: Option<String> = None;
+        let mut flags: Option<String> = None;
+        let mut exported_functions: Vec<String> = vec![];
+
+        for line in content.lines() {
+            let (key, value) = line.split_once('=')?;
+
+            match key {
+                "hash" => source_hash = u64::from_str_radix(value, 16).ok(),
+                "version" => compiler_version = Some(value.to_string()),
+                "flags" => flags = Some(value.to_string()),
+                "export" => exported_functions.push(value.to_string()),
+                _ => return None,
+            }
+        }
+
+        Some(Self {
+            source_hash: source_hash?,
+            compiler_version: compiler_version?,
+            flags: flags?,
+            exported_functions,
+        })
+    }
+
+    /// Is the compiled source file described by `self` the same as what
+    /// `current` would produce ?
+    pub fn is_up_to_date(&self, current: &CacheEntry) -> bool {
+        self.source_hash == current.source_hash
+            && self.compiler_version == current.compiler_version
+            && self.flags == current.flags
+    }
+}
+
+impl fmt::Display for CacheEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "hash={:016x}\nversion={}\nflags={}\n",
+            self.source_hash,
+            self.compiler_version,
+            self.flags
+        )?;
+
+        for symbol in &self.exported_functions {
+            writeln!(f, "export={}", symbol)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Gives the options changing the generated code as one line. The calls to
+/// the other modules are typed with their signatures, so a changed signature
+/// compiles again all the source files
+pub fn give_flags(data: &CompilerData) -> String {
+    let mut signatures: Vec<String> = data.signatures
+        .iter()
+        .map(|(id, signature)| format!("{}{}", id, signature))
+        .collect();
+    // The same hash for the same signatures
+    signatures.sort();
+
+    format!(
+        "{} library={} archive={} libc={} check_division={} optimized={} signatures={:016x}",
+        data.target.triple(),
+        data.is_library,
+        data.is_archive,
+        data.is_libc,
+        data.is_division_checked,
+        data.is_optimized,
+        hash(signatures.join("\n").as_bytes())
+    )
+}
+
+/// FNV-1a hash, stable across the compiler's builds unlike the hasher from
+/// the standard library
+pub fn hash(content: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in content {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+#[test]
+fn cache_entry() {
+    let entry = CacheEntry {
+        source_hash: hash(b"fun main ret"),
+        compiler_version: COMPILER_VERSION.to_string(),
+        flags: "x86_64-linux-gnu library=true libc=false".to_string(),
+        exported_functions: vec!["foo".to_string(), "bar".to_string()],
+    };
+
+    let read = CacheEntry::from_string(&entry.to_string()).unwrap();
+    assert_eq!(read, entry);
+    assert!(read.is_up_to_date(&entry));
+
+    let mut changed = entry.clone();
+    changed.source_hash = hash(b"fun main ret 1");
+    assert!(!changed.is_up_to_date(&entry));
+
+    assert!(CacheEntry::from_string("hash=0\nversion=0.1.0\n").is_none());
+}
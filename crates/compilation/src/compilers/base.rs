@@ -2,27 +2,51 @@
 // Under the MIT License
 // Copyright (c) Junon, Antonin Hérault
 
-use jup::lang::{
-    elements::{
-        function::Function,
-        operation::Operation,
-        variable::Variable,
-        Element,
-    },
-    tokens::Token,
+use std::{
+    path::Path,
+    sync::Mutex,
+    thread,
 };
 
-use x64asm::mnemonic::Mnemonic;
-
-use crate::data::{
-    CompilerData,
-    CompilerTools,
-    CompilerCodeData,
-    CompilerStacksData,
+use jup::{
+    parser::Parser,
+    tokenizer::Tokenizer,
+};
+use rslog::logger::Logger;
+
+use crate::{
+    data::{
+        CompilerData,
+        CompilerTools,
+        CompilerCodeData,
+        CompilerStacksData,
+    },
+    dump::{self, Dump},
+    ir::{
+        check::{self, TypeError},
+        lower::Lowerer,
+        passes,
+        BinaryOperator,
+        Block,
+        BlockId,
+        Callee,
+        Condition,
+        Function,
+        Instruction,
+        LocalId,
+        Module,
+        Temp,
+        Terminator,
+        Value,
+    },
+    scope::Scope,
 };
 
 /// Trait for a Compiler that will be followed by all platform's compilers.
 ///
+/// The parsed elements are lowered into the IR (see `ir`) by cross-platform
+/// code, a platform's compiler only translates the IR to machine code.
+///
 /// Some functions are already defined because they are cross-platform.
 ///
 /// The general documentation is written here to avoid to write the same
@@ -43,105 +67,172 @@ pub trait Compiler {
 
     /// Runs the compiler by calling the initialization and termination 
     /// functions, then compile each source file before doing linkage.
-    fn run(&mut self) {
+    ///
+    /// The source files are compiled independently on `data().jobs` threads, 
+    /// each one by its own compiler created with `fork()`. The forked 
+    /// compilers are joined before the linkage.
+    fn run(&mut self) where Self: Sized + Send {
         self.init();
+        self.collect_signatures();
+
+        let sources = self.data().sources.clone();
+        let jobs = self.data().jobs.clamp(1, sources.len().max(1));
+
+        // Source files waiting to be compiled, with their index to join them in
+        // the same order as given
+        let queue = Mutex::new(
+            sources.iter()
+                .enumerate()
+                .map(|(i, source_path)| (i, source_path.clone(), self.fork()))
+                .collect::<Vec<(usize, String, Self)>>()
+                .into_iter()
+        );
+
+        let mut compiled: Vec<(usize, Self)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs)
+                .map(|_| {
+                    let queue = &queue;
+
+                    scope.spawn(move || {
+                        let mut compiled: Vec<(usize, Self)> = vec![];
+
+                        loop {
+                            // The lock is released before compiling
+                            let next = queue.lock().unwrap().next();
+
+                            match next {
+                                Some((i, source_path, mut compiler)) => {
+                                    compiler.compile_file(&source_path);
+                                    compiled.push((i, compiler));
+                                }
+                                None => break,
+                            }
+                        }
 
-        for source_path in self.data().sources.clone() {
-            // todo!() : Setting the current scope as the source path 
-            // (considering its folder and filename).
+                        compiled
+                    })
+                })
+                .collect();
 
-            self.init_file(&source_path);
-            
-            // Executes calls for the parsed elements from the source file after
-            // having parsed it in `init_file()`.
-            let source_elements = self.code_data().current_parsed.clone();
-            self.call_for_elements(&source_elements);
+            workers.into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
 
-            self.terminate_file(&source_path);
+        compiled.sort_by_key(|(i, _compiler)| *i);
+
+        for (_i, compiler) in compiled {
+            self.join(compiler);
         }
 
         self.link();
         self.terminate();
     }
 
-    /// Links generated files to one output file
-    fn link(&mut self);
+    /// Parses all the source files to retrieve the signatures of their 
+    /// functions in `data().signatures`, a call to a function of another 
+    /// module is then typed like a call to a function of the same module
+    fn collect_signatures(&mut self) {
+        for source_path in self.data().sources.clone() {
+            let mut tokenizer = Tokenizer::from_path(Path::new(&source_path)).unwrap();
+            tokenizer.run();
+
+            let mut parser = Parser::new(tokenizer.tokenized().clone());
+            parser.run();
+
+            let signatures = Lowerer::give_signatures(Scope::from_path(&source_path), parser.parsed());
+            self.data().signatures.extend(signatures);
+        }
+    }
 
-    /// Walks through the given elements, calling `check` for each element.
+    /// Compiles one source file to an object file, except when the object
+    /// file from the previous compilation is still up to date.
     ///
-    /// Skips an element when `is_skip_next` is true.
-    fn call_for_elements(&mut self, elements: &Vec<Element>) {
-        let mut i = 0;
-
-        for element in elements {
-            if self.code_data().is_skip_next {
-                self.code_data().is_skip_next = false;
-                i += 1;
-                continue;
-            }
+    /// A source file with dumps to print is always compiled, the dumps are 
+    /// printed as soon as what they show is known
+    fn compile_file(&mut self, source_path: &String) {
+        let dumps = self.data().dumps.clone();
 
-            if i != elements.len() -1 {
-                self.code_data().next_element = elements[i + 1].clone();
-            }
+        if dumps.is_empty() && !self.data().is_forced && self.is_up_to_date(source_path) {
+            return;
+        }
 
-            self.check_element(element);
-            i += 1;
+        self.init_file(source_path);
+
+        if dumps.contains(&Dump::Tokens) {
+            dump::print(Dump::Tokens, source_path, &dump::give_tokens(&self.code_data().current_tokens));
+        }
+        if dumps.contains(&Dump::Elements) {
+            dump::print(Dump::Elements, source_path, &dump::give_elements(&self.code_data().current_parsed));
         }
+
+        // Lowers the parsed elements from the source file, after having parsed
+        // it in `init_file()`. Each source file is a module named from its 
+        // path
+        let parsed = self.code_data().current_parsed.clone();
+        // The public functions of a shared or static library are both called 
+        // from other languages
+        let is_library = self.data().is_library || self.data().is_archive;
+        let (mut module, lowering_errors) = Lowerer::new(
+            Scope::from_path(source_path), 
+            is_library,
+            self.data().signatures.clone()
+        ).lower(&parsed);
+
+        // The module is not complete, it cannot be checked
+        self.log_type_errors(&lowering_errors, source_path);
+
+        let is_optimized = self.data().is_optimized;
+        passes::run(&mut module, is_optimized);
+
+        self.log_type_errors(&check::check_module(&module), source_path);
+
+        if dumps.contains(&Dump::Scopes) {
+            dump::print(Dump::Scopes, source_path, &dump::give_scopes(&module));
+        }
+        if dumps.contains(&Dump::Ir) {
+            dump::print(Dump::Ir, source_path, &module.to_string());
+        }
+
+        self.at_module(&module);
+
+        if dumps.contains(&Dump::Stack) {
+            dump::print(Dump::Stack, source_path, &self.code_data().current_stack_layout);
+        }
+
+        self.terminate_file(source_path);
     }
 
-    /// Calls to the right function according to the given element.
-    ///
-    /// Note : It's not a logic or syntax checker, it only checks the element to
-    /// call the right function.
-    fn check_element(&mut self, element: &Element) {
-        // All cases where the element raises no call is because we don't care 
-        // about it because we will care it in another function called 
-        // because of another element.
-
-        // End the condition when there is no "else" closure
-        if self.code_data().is_condition || self.code_data().is_loop {
-            match element {
-                Element::Other(Token::NewLine) => {}
-                Element::Operation(_) => {}
-                Element::Expression(_) => {}
-                _ => {
-                    if self.code_data().is_condition 
-                        && element != &Element::Other(Token::ConditionElse) 
-                    {    
-                        self.code_data().is_condition = false;
-                        // When the given token is None, that means the 
-                        // condition has to be ended even when there is no 
-                        // "else" closure
-                        self.at_condition(&Token::None);                        
-                    }
-                    
-                    if self.code_data().is_loop 
-                        && element != &Element::Other(Token::LoopBreak)
-                        && element != &Element::Other(Token::LoopContinue)
-                    {
-                        self.code_data().is_loop = false;
-                        // When the given token is None, that means the loop has 
-                        // to be ended
-                        self.at_loop(&Token::None);     
-                    }
-                }
-            }
+    /// Shows all the type errors of the source file then exits, nothing is done
+    /// when there are no errors
+    fn log_type_errors(&mut self, type_errors: &[TypeError], source_path: &str) {
+        if type_errors.is_empty() {
+            return;
         }
 
-        match element {
-            Element::Array(_values) => {},
-            Element::Assembly(code) => self.at_assembly(code),
-            // Redo a call loop for the elements in the expression found
-            Element::Expression(elements) => self.call_for_elements(elements),
-            Element::Function(function) => self.at_function(function),
-            Element::Operation(operation) => self.at_operation(operation),
-            Element::Return(value) => self.at_return(value),
-            Element::Parameters(_tokens) => {}
-            Element::Variable(variable) => self.at_variable(variable),
-            Element::Other(token) => self.at_other(token),
+        let mut logger = Logger::new();
+        for type_error in type_errors {
+            logger.add_log(type_error.to_log(&self.tools().strings_manager, source_path));
         }
+        logger.interpret();
     }
 
+    /// Checks in the build cache if the source file changed since its last 
+    /// compilation, with the same compiler's version and options.
+    ///
+    /// When it's up to date, retrieves from the cache what the compilation
+    /// would have produced and is needed for linkage
+    fn is_up_to_date(&mut self, source_path: &str) -> bool;
+
+    /// Creates a new compiler with the same `CompilerData`, but with its own 
+    /// tools and code data, to compile one source file
+    fn fork(&self) -> Self where Self: Sized;
+    /// Retrieves what a forked compiler produced and is needed for linkage
+    fn join(&mut self, forked: Self) where Self: Sized;
+
+    /// Links generated files to one output file
+    fn link(&mut self);
+
     // Data getters as it's required -------------------------------------------
     //
     // If the getters are not implemented here it's because they cannot, a 
@@ -152,114 +243,140 @@ pub trait Compiler {
     fn code_data(&mut self) -> &mut CompilerCodeData;
     fn stacks_data(&mut self) -> &mut CompilerStacksData;
 
-    // Functions for the elements ----------------------------------------------
+    // Functions for the IR ----------------------------------------------------
 
-    /// Writes the Assembly code contained into the `code` token in the output
-    /// file.
-    fn at_assembly(&mut self, code: &Token); 
+    /// Translates the IR of the source file
+    fn at_module(&mut self, module: &Module) {
+        for symbol in &module.externs {
+            self.at_extern(symbol);
+        }
 
-    fn at_condition(&mut self, condition: &Token);
+        for code in &module.assembly {
+            self.at_assembly(code);
+        }
+
+        for function in &module.functions {
+            self.at_function(function);
+        }
+    }
 
-    /// Adds a function based on the given object
+    /// Declares a function defined in another object file
+    fn at_extern(&mut self, symbol: &str);
+
+    /// Writes the Assembly code written in the source file
+    fn at_assembly(&mut self, code: &str);
+
+    /// Adds a function based on the given object, then translates its blocks
+    /// with `at_blocks()`
     fn at_function(&mut self, function: &Function);
 
-    fn at_loop(&mut self, token: &Token);
-
-    // Checks the right kind of operation to call the right operation
-    // function
-    fn at_operation(&mut self, operation: &Operation) {
-        match operation.operator() {
-            Token::Assign => self.at_assign(&operation),
-            Token::Plus => self.at_addition(&operation),
-            Token::Minus => self.at_subtraction(&operation),
-            Token::Multiply => self.at_multiply(&operation),
-            Token::Divide => self.at_divide(&operation),
-            Token::Equal => self.at_comparison(&operation),
-            Token::LessThan => self.at_comparison(&operation),
-            Token::MoreThan => self.at_comparison(&operation),
-            Token::LessThanOrEqual => self.at_comparison(&operation),
-            Token::MoreThanOrEqual => self.at_comparison(&operation),
-            _ => panic!("invalid operation for operation : {:?}", operation),
+    fn at_blocks(&mut self, function: &Function) {
+        for (i, block) in function.blocks.iter().enumerate() {
+            let next = function.blocks.get(i + 1).map(|next| next.id);
+            self.at_block(function, block, next);
         }
     }
 
-    fn at_assign(&mut self, operation: &Operation);
-    fn at_addition(&mut self, operation: &Operation);
-    fn at_subtraction(&mut self, operation: &Operation);
-    fn at_multiply(&mut self, operation: &Operation);
-    fn at_divide(&mut self, operation: &Operation);
-    fn at_comparison(&mut self, operation: &Operation);
+    /// Translates the instructions of the block then its terminator.
+    ///
+    /// `next` is the block written just after, so there is no need to jump to 
+    /// it
+    fn at_block(&mut self, function: &Function, block: &Block, next: Option<BlockId>) {
+        self.at_label(block.id);
+
+        // A comparison only used by the branch ending the block is done by the
+        // branch itself
+        let fused = match (block.instructions.last(), &block.terminator) {
+            (
+                Some(Instruction::Compare { dest, condition, left, right }),
+                Terminator::Branch { condition: Value::Temp(temp), then, otherwise },
+            ) if dest == temp && function.count_uses(*temp) == 1 => {
+                Some((*condition, *left, *right, *then, *otherwise))
+            }
+            _ => None,
+        };
 
-    fn at_return(&mut self, value: &Token);
+        let n_instructions = block.instructions.len() - fused.is_some() as usize;
+        for instruction in &block.instructions[..n_instructions] {
+            self.at_instruction(function, instruction);
+        }
 
-    /// Creates a variable by pushing it in the compiler's variable stack
-    ///
-    /// Does call to assign the variable for initialization 
-    fn at_variable(&mut self, variable: &Variable);
+        match fused {
+            Some((condition, left, right, then, otherwise)) => {
+                self.at_compare_branch(condition, left, right, then, otherwise, next);
+            }
+            None => self.at_terminator(&block.terminator, next),
+        }
+    }
 
-    fn at_other(&mut self, other: &Token) {
-        match other {
-            Token::ConditionElse | Token::ConditionIf => {
-                self.at_condition(other);
+    fn at_instruction(&mut self, function: &Function, instruction: &Instruction) {
+        match instruction {
+            Instruction::Copy { dest, value } => self.at_copy(*dest, *value),
+            Instruction::Binary { dest, operator, left, right } => {
+                self.at_binary(*dest, *operator, *left, *right);
             }
-            Token::Loop | Token::LoopBreak | Token::LoopContinue => {
-                self.at_loop(other);
+            Instruction::Compare { dest, condition, left, right } => {
+                self.at_compare(*dest, *condition, *left, *right);
             }
-            Token::NewLine => {},
-            Token::Other(id_or_value) => {
-                match KindToken::from_token(other) {
-                    KindToken::Expression => panic!("impossible, other token cannot be an expression token"),
-                    KindToken::Identifier => {
-                        if self.stacks_data().variable_stack.get(id_or_value).is_none() {
-                            // Not a variable, call the function with this identifier
-                            self.call_function(id_or_value);
-                            return;
-                        }
+            Instruction::Load { dest, local, index } => self.at_load(*dest, *local, *index),
+            Instruction::Cast { dest, value } => self.at_cast(*dest, *value, function),
+            Instruction::Store { local, index, value } => self.at_store(*local, *index, *value),
+            Instruction::Call { dest, callee, arguments } => {
+                self.at_call(*dest, callee, arguments, function);
+            }
+            Instruction::Assembly(code) => self.at_assembly(code),
+            Instruction::Line(_line) => {},
+        }
+    }
 
-                        self.update_return_register(other);
-                    }
-                    KindToken::Value => self.update_return_register(other),
+    fn at_terminator(&mut self, terminator: &Terminator, next: Option<BlockId>) {
+        match terminator {
+            Terminator::Jump(target) => {
+                if Some(*target) != next {
+                    self.at_jump(*target);
                 }
             }
-            _ => panic!("unknown token : {:?}", other),
+            Terminator::Branch { condition, then, otherwise } => {
+                self.at_branch(*condition, *then, *otherwise, next);
+            }
+            Terminator::Return(value) => self.at_return(*value),
         }
     }
 
-    // Other functions for Assembly code ---------------------------------------
+    fn at_label(&mut self, block: BlockId);
+
+    fn at_copy(&mut self, dest: Temp, value: Value);
+    fn at_binary(&mut self, dest: Temp, operator: BinaryOperator, left: Value, right: Value);
+    fn at_compare(&mut self, dest: Temp, condition: Condition, left: Value, right: Value);
+    fn at_load(&mut self, dest: Temp, local: LocalId, index: usize);
+    fn at_store(&mut self, local: LocalId, index: usize, value: Value);
+    /// Converts the value to the type of `dest`, given by the function
+    fn at_cast(&mut self, dest: Temp, value: Value, function: &Function);
+    /// The returned value is given to `dest`, of the type given by the 
+    /// function
+    fn at_call(&mut self, dest: Temp, callee: &Callee, arguments: &[Value], function: &Function);
+
+    fn at_jump(&mut self, target: BlockId);
+    /// Jumps to `then` when `condition` is not zero, to `otherwise` else
+    fn at_branch(&mut self, condition: Value, then: BlockId, otherwise: BlockId, next: Option<BlockId>);
+    /// Compares the two values then jumps to `then` when the condition is 
+    /// true, to `otherwise` else
+    fn at_compare_branch(
+        &mut self, 
+        condition: Condition, 
+        left: Value, 
+        right: Value, 
+        then: BlockId, 
+        otherwise: BlockId, 
+        next: Option<BlockId>
+    );
+    /// Returns zero when there is no value
+    fn at_return(&mut self, value: Option<Value>);
 
-    fn execute_next_expression(&mut self) {
-        let expression = self.code_data().next_element.clone();
-        self.check_element(&expression);
-        self.code_data().is_skip_next = true;
-    }
+    // Other functions for Assembly code ---------------------------------------
 
     fn create_start_function(&mut self);
-    fn call_function(&mut self, id: &String);
-    fn update_return_register(&mut self, value: &Token);
-    fn assign_variable(&mut self, variable: &Variable);
-    fn assign_array_variable(&mut self, array_variable: &Variable);
-    fn give_mnemonic_for_previous_operator(&mut self) -> Mnemonic;
+    /// Code jumped to on a division by zero or on an overflowing division, 
+    /// with "--check-division"
+    fn create_division_errors(&mut self);
 }
-
-#[derive(Debug, Eq, PartialEq)]
-pub enum KindToken {
-    Expression,
-    Identifier,
-    Value,
-}
-
-impl KindToken {
-    pub fn from_token(id_or_value_or_expression: &Token) -> KindToken {
-        if id_or_value_or_expression == &Token::BracketOpen {
-            return KindToken::Expression;
-        }
-    
-        if id_or_value_or_expression.to_string().parse::<f64>().is_ok()
-            || id_or_value_or_expression.to_string().chars().nth(0) == Some('\'') {
-            KindToken::Value
-        } else {
-            KindToken::Identifier
-        }
-    }
-}
-
@@ -3,21 +3,12 @@
 // Copyright (c) Junon, Antonin Hérault
 
 use std::{
+    ffi::OsStr,
     fs,
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use jup::{
-    lang::{
-        elements::{
-            Element,
-            function::Function, 
-            operation::Operation, 
-            type_::Type, 
-            variable::Variable
-        },
-        tokens::Token,
-    },
     parser::Parser,
     tokenizer::Tokenizer,
 };
@@ -40,10 +31,20 @@ use x64asm::{
 };
 
 use args::Args;
-use platform;
+use platform::{
+    self,
+    archive,
+    target::{ CallingConvention, Env },
+};
+use rslog::{
+    level::LogLevel, 
+    log::Log, 
+    logger::Logger
+};
 
 use crate::{
-    compilers::base::{ Compiler, KindToken },
+    cache::CacheEntry,
+    compilers::base::Compiler,
     data::{
         CompilerData,
         CompilerTools,
@@ -52,8 +53,32 @@ use crate::{
     },
     defaults,
     defaults::linux_defaults,
+    dump::{self, Dump},
+    ir::{
+        regalloc::{self, Location, Registers},
+        BinaryOperator,
+        BlockId,
+        Callee,
+        Condition,
+        Function,
+        IrType,
+        LocalId,
+        Temp,
+        Value,
+    },
 };
 
+/// Size in the stack of a temporary or a saved register, a local has the size
+/// of its type
+const SLOT_SIZE: usize = 8;
+
+/// Routine stopping the program on a division by zero, and its message
+const DIVISION_BY_ZERO_LABEL: &str = "__division_by_zero";
+const DIVISION_BY_ZERO_MESSAGE_LABEL: &str = "__division_by_zero_message";
+/// Routine stopping the program on an overflowing division, and its message
+const DIVISION_OVERFLOW_LABEL: &str = "__division_overflow";
+const DIVISION_OVERFLOW_MESSAGE_LABEL: &str = "__division_overflow_message";
+
 /// Compiles for Linux platforms, using the "nasm" assembler
 ///
 /// Uses intel syntax and x64 Assembly
@@ -66,6 +91,11 @@ pub struct LinuxCompiler {
 
     section_data: Vec<Instruction>,
     output_dir: String,
+    /// Does the source file jump to the division errors ?
+    is_division_error_used: bool,
+
+    /// Symbols of all the public functions, exported by a library
+    exported_functions: Vec<String>,
 }
 
 impl LinuxCompiler {
@@ -77,151 +107,883 @@ impl LinuxCompiler {
             stacks_data: all_data.3,
 
             section_data: vec![],
-            output_dir: String::new()
+            output_dir: String::new(),
+            is_division_error_used: false,
+
+            exported_functions: vec![],
         }
     }
 
-    pub fn give_value(&mut self, id_or_value_or_expression: &Token) -> Operand {
-        match KindToken::from_token(id_or_value_or_expression) {
-            KindToken::Expression => {
-                self.execute_next_expression();
-                reg!(defaults::RETURN_REGISTER)
-            },
-            KindToken::Identifier => {
-                let variable_from_id = self.stacks_data().variable_stack
-                    .get(&id_or_value_or_expression.to_string())
-                    .unwrap()
-                    .clone();
+    /// Gives the operand to read a value : the constant itself or where the
+    /// temporary is
+    pub fn give_value(&mut self, value: Value) -> Operand {
+        match value {
+            Value::Temp(temp) => self.give_operand_for_temp(temp),
+            Value::Constant(constant) => Op::Literal(constant),
+        }
+    }
+
+    /// Gives the path of the build cache entry for the source file
+    fn give_cache_path(&self, source_path: &str) -> String {
+        format!("{}/{}.cache", self.output_dir, source_path)
+    }
+
+    /// Gives the path of the copy of the source file which is parsed, so the
+    /// source file can be edited while it's compiled
+    fn give_snapshot_path(&self, source_path: &str) -> String {
+        format!("{}/{}.snapshot", self.output_dir, source_path)
+    }
 
-                self.give_expression_for_variable(&variable_from_id)
+    /// Is the generated code position-independent ? A shared library is loaded
+    /// anywhere, and a static library can be linked in a position-independent
+    /// executable, which is the default of the C compilers
+    fn is_position_independent(&mut self) -> bool {
+        self.data().is_library || self.data().is_archive
+    }
+
+    /// Gives the symbol name for a function's identifier, according to the 
+    /// target's object format
+    pub fn give_symbol(&mut self, id: &str) -> String {
+        format!("{}{}", self.data().target.symbol_prefix(), id)
+    }
+
+    /// Gives the operand for a local, or for one of its elements when it's an
+    /// array. In the stack, the operand has the size of the local's type
+    pub fn give_operand_for_local(&mut self, local: LocalId, index: usize) -> Operand {
+        match self.stacks_data().locals[local.0] {
+            Location::Register(register) => reg!(register),
+            Location::Stack(position) => {
+                let size = self.stacks_data().local_types[local.0].size();
+                give_expression_for_position(position - size * index, size)
             }
-            KindToken::Value => Op::Expression(id_or_value_or_expression.to_string()),
         }
     }
 
-    /// Gives the expression for variable in stack to gets its value
-    pub fn give_expression_for_variable(&mut self, variable: &Variable) -> Operand {
-        Op::Expression(format!(
-            "[{}-{}]", 
-            Register::Rbp.to_string(), 
-            variable.stack_pos()
-        ))
-    }
+    /// Writes a value to a local. A value narrower than 64 bits is written
+    /// from the low part of the scratch register, when it's not a constant
+    fn give_store(&mut self, local: LocalId, index: usize, value: Operand) -> Vec<Instruction> {
+        let dest = self.give_operand_for_local(local, index);
+        let size = self.stacks_data().local_types[local.0].size();
 
-    /// Gives the right register for the current parameter from its index
-    pub fn give_register_for_parameter(&mut self, i_parameter: usize) -> Operand {
-        match i_parameter {
-            0 => reg!(Rcx),
-            1 => reg!(Rdx),
-            2 => reg!(R8),
-            3 => reg!(R9),
-            _ => todo!("only 4 arguments can be given"),
+        if !is_memory(&dest) || size == SLOT_SIZE || matches!(value, Op::Literal(_)) {
+            return give_move(dest, value);
         }
+
+        let mut instructions = give_move(reg!(defaults::SCRATCH_REGISTER), value);
+        instructions.push(i!(Mov, dest, reg!(give_scratch_register(size))));
+        instructions
+    }
+
+    pub fn give_operand_for_temp(&mut self, temp: Temp) -> Operand {
+        give_operand_for_location(self.stacks_data().temps[temp.0])
     }
 
-    pub fn give_type_operand_before_value(&mut self, variable: &Variable, id_or_value_or_expression: &Token) -> Operand {
-        match KindToken::from_token(id_or_value_or_expression) {
-            KindToken::Expression | KindToken::Identifier => {
-                // Nothing because we move a register 
-                Op::None
+    /// Gives the registers which can be given to the values, according to the
+    /// target's calling convention.
+    ///
+    /// The caller-saved registers passing the parameters are not given to the
+    /// values living across a call, neither are the registers used by some 
+    /// instructions (see `defaults`)
+    pub fn give_allocatable_registers(&mut self) -> Registers<Register> {
+        match self.data().target.calling_convention() {
+            CallingConvention::SystemV => Registers {
+                callee_saved: vec![Rbx, R12, R13, R14, R15],
+                caller_saved: vec![Rsi, Rdi, R8, R9, R10],
             },
-            KindToken::Value => {
-                match variable.type_() {
-                    Type::Byte => Op::Byte,
-                    Type::Integer => Op::Word,
-                    Type::BigInteger => Op::Dword,
-                    type_ => panic!("no possible operand for type : {:?}", type_),
+            CallingConvention::Win64 => Registers {
+                callee_saved: vec![Rbx, Rsi, Rdi, R12, R13, R14, R15],
+                caller_saved: vec![R8, R9, R10],
+            },
+            convention => panic!("not an x64 calling convention : {:?}", convention),
+        }
+    }
+
+    /// Gives where each parameter is passed from its type, according to the
+    /// target's calling convention. The parameters which do not fit in the 
+    /// registers are passed in the stack.
+    ///
+    /// With System V, the integers and the floating-point values have their
+    /// own registers. With Win64, the position of a parameter gives its 
+    /// register
+    pub fn give_parameter_locations(&mut self, types: &[IrType]) -> Vec<ParameterLocation> {
+        let convention = self.data().target.calling_convention();
+
+        let (integer_registers, float_registers) = match convention {
+            CallingConvention::SystemV => (
+                vec![Rdi, Rsi, Rdx, Rcx, R8, R9],
+                vec![Xmm0, Xmm1, Xmm2, Xmm3, Xmm4, Xmm5, Xmm6, Xmm7],
+            ),
+            CallingConvention::Win64 => (
+                vec![Rcx, Rdx, R8, R9],
+                vec![Xmm0, Xmm1, Xmm2, Xmm3],
+            ),
+            convention => panic!("not an x64 calling convention : {:?}", convention),
+        };
+        let is_positional = matches!(convention, CallingConvention::Win64);
+
+        let mut locations = vec![];
+        let (mut n_integers, mut n_floats, mut offset) = (0, 0, 0);
+
+        for (i_parameter, type_) in types.iter().enumerate() {
+            let (registers, n_used) = if type_.is_float() {
+                (&float_registers, &mut n_floats)
+            } else {
+                (&integer_registers, &mut n_integers)
+            };
+
+            let i_register = if is_positional { i_parameter } else { *n_used };
+
+            match registers.get(i_register) {
+                Some(register) => {
+                    *n_used += 1;
+                    locations.push(ParameterLocation::Register(*register));
+                }
+                None => {
+                    locations.push(ParameterLocation::Stack(offset));
+                    offset += SLOT_SIZE;
                 }
             }
         }
+
+        locations
     }
 
-    /// Moves the value, when `id_or_value` is the variable's id, to the 
-    /// expression return register, and assign the expression default register 
-    /// to retrieve the value of the variable
-    ///
-    /// So, "id_or_value" should be named "id" here
+    /// Gives the type of a value, a constant is an integer
+    fn give_value_type(&mut self, value: Value) -> IrType {
+        match value {
+            Value::Temp(temp) => self.stacks_data().temp_types[temp.0].clone(),
+            Value::Constant(_constant) => IrType::BigInteger,
+        }
+    }
+
+    fn is_float(&mut self, value: Value) -> bool {
+        self.give_value_type(value).is_float()
+    }
+
+    /// Gives the operand of a floating-point value, always in the stack, with
+    /// the size of its type
+    fn give_float_operand(&mut self, value: Value) -> Operand {
+        let temp = match value {
+            Value::Temp(temp) => temp,
+            Value::Constant(_constant) => panic!("a floating-point constant is copied to a temporary"),
+        };
+
+        match self.stacks_data().temps[temp.0] {
+            Location::Stack(position) => {
+                let size = self.stacks_data().temp_types[temp.0].size();
+                give_expression_for_position(position, size)
+            }
+            Location::Register(_register) => panic!("a floating-point value is in the stack"),
+        }
+    }
+
+    /// Gives the number of bytes reserved by the caller just above the return
+    /// address, for the called function to save its parameters' registers
+    pub fn give_shadow_space(&mut self) -> usize {
+        match self.data().target.calling_convention() {
+            CallingConvention::Win64 => 32,
+            _ => 0,
+        }
+    }
+
+    /// Archives the object files into a static library instead of linking 
+    /// them.
     ///
-    /// Returns if it was an identifier or not
-    fn before_getting_value_when_id(&mut self, id_or_value: &Token, to_register: Register) -> bool {        
-        // Not an identifier, nothing to do
-        if KindToken::from_token(id_or_value) != KindToken::Identifier {
-            return false;
+    /// The object files given with "-a" are archived too
+    fn archive(&mut self, output_path: &str) {
+        let mut members: Vec<PathBuf> = vec![];
+
+        for source_path in &self.data.sources {
+            members.push(PathBuf::from(format!("{}/{}.o", self.output_dir, source_path)));
         }
 
-        let instruction = i!(
-            Mov,
-            reg!(to_register),
-            {
-                let value_as_variable = self.stacks_data().variable_stack
-                    .get(&id_or_value.to_string())
-                    .unwrap()
-                    .clone();
-                
-                self.give_expression_for_variable(&value_as_variable)
+        Args::when_flag('a', &self.data().options, |to_add: String| {
+            if Path::new(&to_add).extension() == Some(OsStr::new("o")) {
+                members.push(PathBuf::from(to_add));
             }
+        });
+
+        if let Err(error) = archive::create(Path::new(output_path), &members) {
+            let sm = self.tools().strings_manager.clone();
+
+            let mut logger = Logger::new();
+            logger.add_log(
+                Log::new(
+                    LogLevel::Error,
+                    sm.get().logs.errors.archive_creation_failed.title.as_ref().unwrap().get(&sm)
+                        .replacen("{}", output_path, 1),
+                    error.to_string()
+                )
+            );
+            logger.interpret();
+        }
+    }
+
+    fn log_c_runtime_not_found(&mut self) {
+        let sm = self.tools().strings_manager.clone();
+
+        let mut logger = Logger::new();
+        logger.add_log(
+            Log::new(
+                LogLevel::Error,
+                sm.get().logs.errors.c_runtime_not_found.title.as_ref().unwrap().get(&sm),
+                sm.get().logs.errors.c_runtime_not_found.message.as_ref().unwrap().get(&sm)
+                    .replacen("{}", &self.give_c_runtime_directories().join(", "), 1)
+            )
+            .add_hint(
+                sm.get().logs.errors.c_runtime_not_found.hint.as_ref().unwrap().get(&sm)
+            )
         );
+        logger.interpret();
+    }
 
-        self.tools().asm_formatter.add_instruction(instruction);
-        return true;
+    /// Gives the directories where the C runtime object files are searched,
+    /// according to the C library of the target's environment
+    fn give_c_runtime_directories(&mut self) -> &'static [&'static str] {
+        match self.data().target.env {
+            Env::Musl => &linux_defaults::MUSL_C_RUNTIME_DIRECTORIES,
+            _ => &linux_defaults::GNU_C_RUNTIME_DIRECTORIES,
+        }
     }
 
-    fn do_arithmetic_operation(&mut self, operation: &Operation, operation_mnemonic: Mnemonic) {
-        let arg1_value = self.give_value(operation.arg1());
-        let arg2_value = self.give_value(operation.arg2());
+    /// Gives the directory where the C runtime object files are, or `None` 
+    /// when they cannot be found
+    fn give_c_runtime_directory(&mut self) -> Option<String> {
+        self.give_c_runtime_directories()
+            .iter()
+            .find(|directory| Path::new(directory).join("crt1.o").exists())
+            .map(|directory| directory.to_string())
+    }
 
-        let is_arg1_identifier = KindToken::from_token(operation.arg1()) == KindToken::Identifier;
-        let is_arg2_identifier = KindToken::from_token(operation.arg2()) == KindToken::Identifier;
+    /// Computes an operation on two integers or two floating-point values
+    fn give_binary(
+        &mut self, 
+        dest: Temp, 
+        operator: BinaryOperator, 
+        left: Value, 
+        right: Value
+    ) -> Vec<Instruction> {
+        if self.is_float(Value::Temp(dest)) {
+            let type_ = self.give_value_type(Value::Temp(dest));
+            let is_double = type_ == IrType::Double;
+
+            let mnemonic = match (operator, is_double) {
+                (BinaryOperator::Add, true) => Addsd,
+                (BinaryOperator::Add, false) => Addss,
+                (BinaryOperator::Subtract, true) => Subsd,
+                (BinaryOperator::Subtract, false) => Subss,
+                (BinaryOperator::Multiply, true) => Mulsd,
+                (BinaryOperator::Multiply, false) => Mulss,
+                (BinaryOperator::Divide, true) => Divsd,
+                (BinaryOperator::Divide, false) => Divss,
+                _ => panic!("unsupported operation on floating-point values : {}", operator),
+            };
 
-        let mut instructions = if is_arg1_identifier && is_arg2_identifier {
-            vec![
-                i!(
-                    Mov, 
-                    reg!(defaults::RETURN_REGISTER_2), 
-                    arg1_value
-                ),
-                i!(
-                    Mov, 
-                    reg!(defaults::RETURN_REGISTER), 
-                    arg2_value
-                ),
-                i!(
-                    operation_mnemonic,
-                    reg!(defaults::RETURN_REGISTER), 
-                    reg!(defaults::RETURN_REGISTER_2)
-                )
-            ]
+            return vec![
+                i!(give_float_move(&type_), reg!(Xmm0), self.give_float_operand(left)),
+                i!(mnemonic, reg!(Xmm0), self.give_float_operand(right)),
+                i!(give_float_move(&type_), self.give_float_operand(Value::Temp(dest)), reg!(Xmm0)),
+            ];
+        }
+
+        // A result narrower than 64 bits is truncated to its type in the 
+        // scratch register, then extended again as every temporary
+        let type_ = self.give_value_type(Value::Temp(dest));
+        let is_narrow = type_.size() < SLOT_SIZE;
+
+        let dest = self.give_operand_for_temp(dest);
+        let left = self.give_value(left);
+        let mut right = self.give_value(right);
+
+        let mut instructions = vec![];
+
+        let mnemonic = match operator {
+            BinaryOperator::Add => Add,
+            BinaryOperator::Subtract => Sub,
+            BinaryOperator::Multiply => Imul,
+            BinaryOperator::BitwiseAnd => And,
+            BinaryOperator::BitwiseOr => Or,
+            BinaryOperator::BitwiseXor => Xor,
+            BinaryOperator::ShiftLeft => Shl,
+            BinaryOperator::ShiftRight => Sar,
+            BinaryOperator::UnsignedShiftRight => Shr,
+            // The dividend is sign-extended into "rdx:rax", or zero-extended
+            // when unsigned
+            BinaryOperator::Divide
+            | BinaryOperator::Modulo
+            | BinaryOperator::UnsignedDivide
+            | BinaryOperator::UnsignedModulo => {
+                let is_signed = matches!(operator, BinaryOperator::Divide | BinaryOperator::Modulo);
+                // Only -1 can overflow as the divisor
+                let is_overflow_checked = self.data().is_division_checked
+                    && is_signed
+                    && !matches!(right, Op::Literal(divisor) if divisor != -1);
+
+                // The divisor cannot be a constant
+                if matches!(right, Op::Literal(_)) {
+                    instructions.push(i!(Mov, reg!(defaults::SCRATCH_REGISTER), right));
+                    right = reg!(defaults::SCRATCH_REGISTER);
+                }
+
+                instructions.push(i!(Mov, reg!(Rax), left));
+
+                if self.data().is_division_checked {
+                    instructions.append(&mut vec![
+                        i!(Cmp, right.clone(), Op::Literal(0)),
+                        i!(Je, Op::Label(DIVISION_BY_ZERO_LABEL.to_string())),
+                    ]);
+                    self.is_division_error_used = true;
+                }
+
+                // The smallest value of the type divided by -1 does not fit 
+                // in the type : both "rdx" and "rcx" are zero when the 
+                // dividend is the smallest value and the divisor is -1
+                if is_overflow_checked {
+                    let (smallest, _biggest) = type_.range().unwrap();
+
+                    instructions.append(&mut vec![
+                        i!(Mov, reg!(Rdx), Op::Literal(smallest as i64)),
+                        i!(Xor, reg!(Rdx), reg!(Rax)),
+                        i!(Mov, reg!(Rcx), right.clone()),
+                        i!(Add, reg!(Rcx), Op::Literal(1)),
+                        i!(Or, reg!(Rdx), reg!(Rcx)),
+                        i!(Je, Op::Label(DIVISION_OVERFLOW_LABEL.to_string())),
+                    ]);
+                }
+
+                if is_signed {
+                    instructions.append(&mut vec![i!(Cqo), i!(Idiv, right)]);
+                } else {
+                    instructions.append(&mut vec![i!(Xor, reg!(Rdx), reg!(Rdx)), i!(Div, right)]);
+                }
+
+                let result = match operator {
+                    BinaryOperator::Divide | BinaryOperator::UnsignedDivide => Rax,
+                    _ => Rdx,
+                };
+                if is_narrow {
+                    instructions.append(&mut vec![
+                        i!(Mov, reg!(defaults::SCRATCH_REGISTER), reg!(result)),
+                        give_truncation(&type_),
+                    ]);
+                    instructions.append(&mut give_move(dest, reg!(defaults::SCRATCH_REGISTER)));
+                } else {
+                    instructions.append(&mut give_move(dest, reg!(result)));
+                }
+
+                return instructions;
+            }
+        };
+
+        if matches!(
+            operator,
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight | BinaryOperator::UnsignedShiftRight
+        ) {
+            right = match right {
+                Op::Literal(count) => Op::Literal(count & 63),
+                right => {
+                    instructions.push(i!(Mov, reg!(Rcx), right));
+                    reg!(Cl)
+                }
+            };
+        } else if !is_immediate(&right) {
+            instructions.push(i!(Mov, reg!(defaults::SCRATCH_REGISTER_2), right));
+            right = reg!(defaults::SCRATCH_REGISTER_2);
+        }
+
+        let result = if is_memory(&dest) || is_narrow {
+            reg!(defaults::SCRATCH_REGISTER)
         } else {
-            vec![
-                i!(
-                    Mov, 
-                    reg!(defaults::RETURN_REGISTER), 
-                    arg1_value
-                ),
-                i!(
-                    operation_mnemonic, 
-                    reg!(defaults::RETURN_REGISTER), 
-                    arg2_value
-                ),
-            ]
+            dest.clone()
+        };
+
+        instructions.append(&mut give_move(result.clone(), left));
+        instructions.push(i!(mnemonic, result.clone(), right));
+        if is_narrow {
+            instructions.push(give_truncation(&type_));
+        }
+        instructions.append(&mut give_move(dest, result));
+
+        instructions
+    }
+
+    /// Calls a function, the arguments which do not fit in the registers are
+    /// pushed in the stack, which stays aligned on 16 bytes
+    fn give_call(&mut self, dest: Temp, callee: &Callee, arguments: &[Value]) -> Vec<Instruction> {
+        let types: Vec<IrType> = arguments.iter()
+            .map(|argument| self.give_value_type(*argument))
+            .collect();
+        let locations = self.give_parameter_locations(&types);
+        let shadow_space = self.give_shadow_space();
+
+        let n_pushed = locations.iter()
+            .filter(|location| matches!(location, ParameterLocation::Stack(_)))
+            .count();
+        let padding = (n_pushed * SLOT_SIZE) % linux_defaults::STACK_ALIGNMENT;
+        let mut instructions = vec![];
+
+        if padding != 0 {
+            instructions.push(i!(Sub, reg!(Rsp), Op::Literal(padding as i64)));
+        }
+
+        for (argument, location) in arguments.iter().zip(&locations).rev() {
+            if !matches!(location, ParameterLocation::Stack(_)) {
+                continue;
+            }
+            let argument = self.give_value(*argument);
+
+            if is_immediate(&argument) {
+                instructions.push(i!(Push, argument));
+            } else {
+                instructions.append(&mut vec![
+                    i!(Mov, reg!(defaults::SCRATCH_REGISTER), argument),
+                    i!(Push, reg!(defaults::SCRATCH_REGISTER)),
+                ]);
+            }
+        }
+
+        if shadow_space != 0 {
+            instructions.push(i!(Sub, reg!(Rsp), Op::Literal(shadow_space as i64)));
+        }
+
+        // The floating-point values are in the stack, so they are given once
+        // the other parameters are in their registers
+        let mut moves = vec![];
+        let mut float_moves = vec![];
+
+        for ((argument, type_), location) in arguments.iter().zip(&types).zip(&locations) {
+            match location {
+                ParameterLocation::Register(register) if type_.is_float() => float_moves.push(i!(
+                    give_float_move(type_),
+                    reg!(*register),
+                    self.give_float_operand(*argument)
+                )),
+                ParameterLocation::Register(register) => {
+                    moves.push((*register, self.give_value(*argument)));
+                }
+                ParameterLocation::Stack(_offset) => {}
+            }
+        }
+
+        let n_vector_registers = float_moves.len();
+        instructions.append(&mut give_parallel_move(moves));
+        instructions.append(&mut float_moves);
+
+        // Number of vector registers used for the parameters, required by the
+        // C functions with a variable number of parameters
+        if callee.is_extern && self.data().is_libc {
+            let instruction = if n_vector_registers == 0 {
+                i!(Xor, reg!(Eax), reg!(Eax))
+            } else {
+                i!(Mov, reg!(Eax), Op::Literal(n_vector_registers as i64))
+            };
+            instructions.push(instruction);
+        }
+
+        // Position-independent code calls the external functions through the
+        // procedure linkage table
+        let symbol = if callee.is_extern && self.is_position_independent() {
+            format!("{} wrt ..plt", self.give_symbol(&callee.symbol))
+        } else {
+            self.give_symbol(&callee.symbol)
         };
 
+        instructions.push(i!(Call, Op::Label(symbol)));
+
+        let freed = shadow_space + n_pushed * SLOT_SIZE + padding;
+        if freed != 0 {
+            instructions.push(i!(Add, reg!(Rsp), Op::Literal(freed as i64)));
+        }
+
+        let type_ = self.give_value_type(Value::Temp(dest));
+        if type_.is_float() {
+            instructions.push(i!(
+                give_float_move(&type_),
+                self.give_float_operand(Value::Temp(dest)),
+                reg!(Xmm0)
+            ));
+            return instructions;
+        }
+
+        let returned = if callee.is_extern && type_.size() != 0 && type_.size() < SLOT_SIZE {
+            let returned = match type_.size() {
+                1 => Al,
+                _ => Eax,
+            };
+            instructions.push(give_extension(reg!(returned), &type_));
+            defaults::SCRATCH_REGISTER
+        } else {
+            defaults::FUN_RETURN_REGISTER
+        };
+
+        instructions.append(&mut give_move(self.give_operand_for_temp(dest), reg!(returned)));
+        instructions
+    }
+
+    /// Creates the routine at `label` writing the error message to the error
+    /// output, then exiting
+    fn create_error_routine(&mut self, label: &str, message_label: &str, message: &str) {
+        let line = format!("{}\n", message);
+
+        self.section_data.push(i!(Expression(format!(
+            "{} db \"{}\", 10",
+            message_label,
+            message
+        ))));
+
+        self.tools().asm_formatter.add_instructions(&mut vec![
+            i!(section!(Text)),
+            i!(label!(label.to_string())),
+            // write(2, message, length)
+            i!(Mov, reg!(Rax), Op::Literal(1)),
+            i!(Mov, reg!(Rdi), Op::Literal(2)),
+            i!(Lea, reg!(Rsi), Op::Expression(format!("[{}]", message_label))),
+            i!(Mov, reg!(Rdx), Op::Literal(line.len() as i64)),
+            i!(Syscall),
+            // exit(code)
+            i!(Mov, reg!(Rax), Op::Literal(60)),
+            i!(Mov, reg!(Rdi), Op::Literal(linux_defaults::DIVISION_ERROR_EXIT_CODE)),
+            i!(Syscall),
+        ]);
+    }
+
+    /// Compares two floating-point values in a vector register, the flags are
+    /// set as for a comparison of unsigned values. Returns the condition to
+    /// test on the flags.
+    ///
+    /// A comparison with NaN is unordered and sets the zero, parity and carry
+    /// flags, as "equal" and "below". So "<" and "<=" are tested as ">" and
+    /// ">=" with the operands swapped, and "==" and "!=" also test the parity
+    /// flag, see `at_compare()` and `at_compare_branch()`
+    fn give_float_comparison(
+        &mut self, 
+        condition: Condition, 
+        left: Value, 
+        right: Value
+    ) -> (Vec<Instruction>, Condition) {
+        let (left, right, condition) = match condition {
+            Condition::LessThan => (right, left, Condition::Above),
+            Condition::LessThanOrEqual => (right, left, Condition::AboveOrEqual),
+            condition => (left, right, condition.to_unsigned()),
+        };
+
+        let type_ = self.give_value_type(left);
+        let compare = if type_ == IrType::Float { Ucomiss } else { Ucomisd };
+
+        let instructions = vec![
+            i!(give_float_move(&type_), reg!(Xmm0), self.give_float_operand(left)),
+            i!(compare, reg!(Xmm0), self.give_float_operand(right)),
+        ];
+
+        (instructions, condition)
+    }
+
+    /// Compares two integer values. The left one is moved to the scratch 
+    /// register when it's a constant or when both are in memory
+    fn give_comparison(&mut self, left: Value, right: Value) -> Vec<Instruction> {
+        let mut left = self.give_value(left);
+        let mut right = self.give_value(right);
+
+        let mut instructions = vec![];
+
+        if matches!(left, Op::Literal(_)) || (is_memory(&left) && is_memory(&right)) {
+            instructions.push(i!(Mov, reg!(defaults::SCRATCH_REGISTER), left));
+            left = reg!(defaults::SCRATCH_REGISTER);
+        }
+
+        if !is_immediate(&right) {
+            instructions.push(i!(Mov, reg!(defaults::SCRATCH_REGISTER_2), right));
+            right = reg!(defaults::SCRATCH_REGISTER_2);
+        }
+
+        instructions.push(i!(Cmp, left, right));
+        instructions
+    }
+
+    /// Jumps to `then` when the previous comparison is true, to `otherwise` 
+    /// else, without jumping to the next block
+    fn jump_for_condition(
+        &mut self, 
+        condition: Condition, 
+        then: BlockId, 
+        otherwise: BlockId, 
+        next: Option<BlockId>
+    ) {
+        let mut instructions = if Some(then) == next {
+            vec![i!(give_inverse_jump_mnemonic(condition), Op::Label(give_label(otherwise)))]
+        } else {
+            vec![i!(give_jump_mnemonic(condition), Op::Label(give_label(then)))]
+        };
+
+        if Some(then) != next && Some(otherwise) != next {
+            instructions.push(i!(Jmp, Op::Label(give_label(otherwise))));
+        }
+
         self.tools().asm_formatter.add_instructions(&mut instructions);
     }
+
+    /// Converts between an integer and a floating-point value, or between a
+    /// float and a double
+    fn give_float_cast(
+        &mut self, 
+        dest: Temp, 
+        value: Value, 
+        source_type: &IrType, 
+        dest_type: &IrType
+    ) -> Vec<Instruction> {
+        let dest_value = Value::Temp(dest);
+
+        // From an integer, through the scratch register
+        if !source_type.is_float() {
+            let convert = if dest_type == &IrType::Float { Cvtsi2ss } else { Cvtsi2sd };
+
+            return vec![
+                i!(Mov, reg!(defaults::SCRATCH_REGISTER), self.give_value(value)),
+                i!(convert, reg!(Xmm0), reg!(defaults::SCRATCH_REGISTER)),
+                i!(give_float_move(dest_type), self.give_float_operand(dest_value), reg!(Xmm0)),
+            ];
+        }
+
+        // To an integer, truncated then extended from the size of its type
+        if !dest_type.is_float() {
+            let convert = if source_type == &IrType::Float { Cvttss2si } else { Cvttsd2si };
+
+            let mut instructions = vec![
+                i!(convert, reg!(defaults::SCRATCH_REGISTER), self.give_float_operand(value)),
+            ];
+            if dest_type.size() < SLOT_SIZE {
+                instructions.push(give_extension(
+                    reg!(give_scratch_register(dest_type.size())), 
+                    dest_type
+                ));
+            }
+            instructions.append(&mut give_move(
+                self.give_operand_for_temp(dest),
+                reg!(defaults::SCRATCH_REGISTER)
+            ));
+            return instructions;
+        }
+
+        let convert = match (source_type, dest_type) {
+            (IrType::Float, IrType::Double) => Cvtss2sd,
+            (IrType::Double, IrType::Float) => Cvtsd2ss,
+            _ => give_float_move(source_type),
+        };
+
+        vec![
+            i!(convert, reg!(Xmm0), self.give_float_operand(value)),
+            i!(give_float_move(dest_type), self.give_float_operand(dest_value), reg!(Xmm0)),
+        ]
+    }
 }
 
+/// Gives the memory operand of `size` bytes at the position from the base
+/// pointer
+fn give_expression_for_position(position: usize, size: usize) -> Operand {
+    let size_keyword = match size {
+        1 => "byte",
+        2 => "word",
+        4 => "dword",
+        8 => "qword",
+        size => panic!("no memory operand of {} bytes", size),
+    };
+
+    Op::Expression(format!("{} [{}-{}]", size_keyword, Register::Rbp.to_string(), position))
+}
+
+/// Gives the parameter passed in the stack at `offset` bytes after the first
+/// one, above the saved base pointer and the return address
+fn give_expression_for_stack_parameter(offset: usize) -> Operand {
+    Op::Expression(format!("qword [{}+{}]", Register::Rbp.to_string(), 2 * SLOT_SIZE + offset))
+}
+
+fn give_operand_for_location(location: Location<Register>) -> Operand {
+    match location {
+        Location::Register(register) => reg!(register),
+        Location::Stack(position) => give_expression_for_position(position, SLOT_SIZE),
+    }
+}
+
+/// Moves all the sources to their destination register as if it was done at
+/// the same time : a register is written only once no other move reads it.
+///
+/// When the moves read each other's destination in a cycle, the value of one
+/// destination is saved in the scratch register first
+fn give_parallel_move(mut moves: Vec<(Register, Operand)>) -> Vec<Instruction> {
+    let mut instructions = vec![];
+    moves.retain(|(dest, src)| &reg!(*dest) != src);
+
+    while !moves.is_empty() {
+        let free = moves.iter().position(|(dest, _src)| {
+            !moves.iter().any(|(_dest, src)| src == &reg!(*dest))
+        });
+
+        match free {
+            Some(i_move) => {
+                let (dest, src) = moves.remove(i_move);
+                instructions.push(i!(Mov, reg!(dest), src));
+            }
+            None => {
+                let saved = moves[0].0;
+                instructions.push(i!(Mov, reg!(defaults::SCRATCH_REGISTER), reg!(saved)));
+
+                for (_dest, src) in moves.iter_mut() {
+                    if src == &reg!(saved) {
+                        *src = reg!(defaults::SCRATCH_REGISTER);
+                    }
+                }
+            }
+        }
+    }
+
+    instructions
+}
+
+/// Where a parameter is passed, in a register or in the stack at an offset
+/// from the first parameter passed in the stack
+pub enum ParameterLocation {
+    Register(Register),
+    Stack(usize),
+}
+
+/// Gives the move of a floating-point value of the type, between a vector 
+/// register and memory
+fn give_float_move(type_: &IrType) -> Mnemonic {
+    match type_ {
+        IrType::Float => Movss,
+        _ => Movsd,
+    }
+}
+
+/// Gives the part of the scratch register of `size` bytes
+fn give_scratch_register(size: usize) -> Register {
+    match size {
+        1 => R11b,
+        2 => R11w,
+        4 => R11d,
+        _ => defaults::SCRATCH_REGISTER,
+    }
+}
+
+/// Reads a value narrower than 64 bits to the whole scratch register, 
+/// sign-extended or zero-extended according to its type
+fn give_extension(source: Operand, type_: &IrType) -> Instruction {
+    match type_ {
+        IrType::Byte => i!(Movsx, reg!(defaults::SCRATCH_REGISTER), source),
+        IrType::Integer => i!(Movsxd, reg!(defaults::SCRATCH_REGISTER), source),
+        IrType::UnsignedByte => i!(Movzx, reg!(defaults::SCRATCH_REGISTER), source),
+        // Writing a 32 bits register clears the upper bits
+        IrType::UnsignedInteger | IrType::Float => i!(Mov, reg!(R11d), source),
+        _ => i!(Mov, reg!(defaults::SCRATCH_REGISTER), source),
+    }
+}
+
+/// Truncates the value in the scratch register to the type narrower than 64
+/// bits, then extends it again to the whole register : an "uint" computed as
+/// `(p << 4) >> 4` has its 4 upper bits cleared
+fn give_truncation(type_: &IrType) -> Instruction {
+    give_extension(reg!(give_scratch_register(type_.size())), type_)
+}
+
+/// Moves a value, through the scratch register when the instruction cannot
+/// be written directly : from memory to memory, or a constant too big to be
+/// written in memory
+fn give_move(dest: Operand, src: Operand) -> Vec<Instruction> {
+    if dest == src {
+        return vec![];
+    }
+
+    if is_memory(&dest) && (is_memory(&src) || !is_immediate(&src)) {
+        return vec![
+            i!(Mov, reg!(defaults::SCRATCH_REGISTER), src),
+            i!(Mov, dest, reg!(defaults::SCRATCH_REGISTER)),
+        ];
+    }
+
+    vec![i!(Mov, dest, src)]
+}
+
+fn is_memory(operand: &Operand) -> bool {
+    matches!(operand, Op::Expression(_))
+}
+
+/// Can the operand be written as the source of an instruction ? A constant
+/// has to fit in 32 bits, it's sign-extended to 64 bits
+fn is_immediate(operand: &Operand) -> bool {
+    match operand {
+        Op::Literal(constant) => i32::try_from(*constant).is_ok(),
+        _ => true,
+    }
+}
+
+/// Gives the label of a block, local to its function
+fn give_label(block: BlockId) -> String {
+    format!(".block_{}", block.0)
+}
+
+fn give_set_mnemonic(condition: Condition) -> Mnemonic {
+    match condition {
+        Condition::Equal => Sete,
+        Condition::NotEqual => Setne,
+        Condition::LessThan => Setl,
+        Condition::MoreThan => Setg,
+        Condition::LessThanOrEqual => Setle,
+        Condition::MoreThanOrEqual => Setge,
+        Condition::Below => Setb,
+        Condition::Above => Seta,
+        Condition::BelowOrEqual => Setbe,
+        Condition::AboveOrEqual => Setae,
+    }
+}
+
+/// Jumps when the condition is true
+fn give_jump_mnemonic(condition: Condition) -> Mnemonic {
+    match condition {
+        Condition::Equal => Je,
+        Condition::NotEqual => Jne,
+        Condition::LessThan => Jl,
+        Condition::MoreThan => Jg,
+        Condition::LessThanOrEqual => Jle,
+        Condition::MoreThanOrEqual => Jge,
+        Condition::Below => Jb,
+        Condition::Above => Ja,
+        Condition::BelowOrEqual => Jbe,
+        Condition::AboveOrEqual => Jae,
+    }
+}
+
+/// Jumps when the condition is false
+fn give_inverse_jump_mnemonic(condition: Condition) -> Mnemonic {
+    match condition {
+        Condition::Equal => Jne,
+        Condition::NotEqual => Je,
+        Condition::LessThan => Jge,
+        Condition::MoreThan => Jle,
+        Condition::LessThanOrEqual => Jg,
+        Condition::MoreThanOrEqual => Jl,
+        Condition::Below => Jae,
+        Condition::Above => Jbe,
+        Condition::BelowOrEqual => Ja,
+        Condition::AboveOrEqual => Jb,
+    }
+}
 
 /// See some functions' documentations on the `Compiler` page because they are 
 /// not written here already
 impl Compiler for LinuxCompiler {
     fn init(&mut self) {
-        let mut output_dir = String::new();
+        let mut output_dir = ".".to_string();
 
         Args::when_flag('o', &self.data().options, |args_output_path: String| {
             let output_dir_path = Path::new(&args_output_path)
                 .parent()
                 .unwrap();
 
+            // The output file is in the current directory
+            if output_dir_path.as_os_str().is_empty() {
+                return;
+            }
+
             output_dir = output_dir_path.to_str()
                 .unwrap()
                 .to_string();
@@ -230,12 +992,50 @@ impl Compiler for LinuxCompiler {
         });
 
         self.output_dir = output_dir;
-    
-        self.tools().asm_formatter.add_instruction(i!(section!(Text)));
     }
 
     fn terminate(&mut self) {}
 
+    fn is_up_to_date(&mut self, source_path: &str) -> bool {
+        let object_path = format!("{}/{}.o", self.output_dir, source_path);
+        if !Path::new(&object_path).exists() {
+            return false;
+        }
+
+        let cached = match CacheEntry::from_file(Path::new(&self.give_cache_path(source_path))) {
+            Some(cached) => cached,
+            None => return false,
+        };
+
+        let current = match fs::read(source_path) {
+            Ok(source) => CacheEntry::new(&source, &self.data),
+            Err(_) => return false,
+        };
+
+        if !cached.is_up_to_date(&current) {
+            return false;
+        }
+
+        self.exported_functions = cached.exported_functions;
+        true
+    }
+
+    fn fork(&self) -> Self {
+        let mut compiler = Self::new((
+            self.data.clone(),
+            CompilerTools::new(self.tools.strings_manager.clone()),
+            CompilerCodeData::new(),
+            CompilerStacksData::new(),
+        ));
+
+        compiler.output_dir = self.output_dir.clone();
+        compiler
+    }
+
+    fn join(&mut self, forked: Self) {
+        self.exported_functions.extend(forked.exported_functions);
+    }
+
     /// Creates an Assembly file for the source file.
     ///
     /// Parses file's content and sets `self.code_data().current_parsed`.  
@@ -249,25 +1049,51 @@ impl Compiler for LinuxCompiler {
             source_path
         );
         self.code_data().current_source = output_file_path.clone();
+
+        // Reads the source file once, the build cache entry is written for 
+        // these exact bytes even if the file changes during the compilation
+        let snapshot_path = self.give_snapshot_path(source_path);
+        self.code_data().current_content = fs::read(source_path).unwrap();
+
+        fs::create_dir_all(Path::new(&snapshot_path).parent().unwrap())
+            .unwrap();
+        fs::write(&snapshot_path, &self.code_data().current_content).unwrap();
         
         // Parses the tokens to something that could be used by the compiler 
         // (elements) 
         let mut parser = Parser::new({
-            // Reads the source file content and transforms it into tokens
-            let mut tokenizer = Tokenizer::from_path(Path::new(source_path))
+            // Transforms the read content into tokens
+            let mut tokenizer = Tokenizer::from_path(Path::new(&snapshot_path))
                 .unwrap();
             tokenizer.run();
-            tokenizer.tokenized().clone()
+
+            self.code_data().current_tokens = tokenizer.tokenized().clone();
+            self.code_data().current_tokens.clone()
         });
         parser.run();
 
         self.code_data().current_parsed = parser.parsed().clone();
+
+        // A shared or static library is position-independent code : all the
+        // memory accesses to symbols are relative to the instruction pointer
+        if self.is_position_independent() {
+            self.tools().asm_formatter.add_instruction(
+                i!(Expression("default rel".to_string()))
+            );
+        }
+
+        self.tools().asm_formatter.add_instruction(i!(section!(Text)));
     }
 
     /// Terminates to write some Assembly code if needed (data sections)
     ///
     /// Assembles Assembly code to an object file to be linked  
     fn terminate_file(&mut self, source_path: &String) {
+        if self.is_division_error_used {
+            self.create_division_errors();
+            self.is_division_error_used = false;
+        }
+
         // Adds all items from the data section
         if !self.section_data.is_empty() {
             self.tools().asm_formatter.add_instruction(
@@ -292,15 +1118,19 @@ impl Compiler for LinuxCompiler {
         self.tools().asm_formatter.reset();
 
         // Assembles to an object file
-        platform::exec(
-            linux_defaults::ASSEMBLER.to_string(),
-            &[
-                format!("{}/{}.asm", self.output_dir, source_path),
-                "-felf64".to_string(),
-                "-o".to_string(),
-                format!("{}/{}.o", self.output_dir, source_path),
-            ],
-        );
+        let mut args = vec![format!("{}/{}.asm", self.output_dir, source_path)];
+        args.append(&mut self.data().target.assembler_flags());
+        args.push("-o".to_string());
+        args.push(format!("{}/{}.o", self.output_dir, source_path));
+
+        platform::exec(linux_defaults::ASSEMBLER.to_string(), &args);
+
+        // Up to date until the next changes
+        let content = self.code_data().current_content.clone();
+        let mut cache_entry = CacheEntry::new(&content, &self.data);
+        cache_entry.exported_functions = self.exported_functions.clone();
+        cache_entry.to_file(Path::new(&self.give_cache_path(source_path)))
+            .unwrap();
     }
 
     fn link(&mut self) {
@@ -310,7 +1140,12 @@ impl Compiler for LinuxCompiler {
             output_path = args_output_path;
         });
 
-        let mut args = vec!["-o".to_string(), output_path];
+        if self.data().is_archive {
+            self.archive(&output_path);
+            return;
+        }
+
+        let mut args = vec!["-o".to_string(), output_path.clone()];
         
         // Maybe there are files to link within
         Args::when_flag('a', &self.data().options, |to_add: String| {
@@ -318,13 +1153,69 @@ impl Compiler for LinuxCompiler {
         });
         
         if self.data().is_library {
+            let soname = Path::new(&output_path)
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+
             args.push("-shared".to_string());
+            args.push("-soname".to_string());
+            args.push(soname);
+
+            // Only the public functions are exported, everything else is local
+            // to the library
+            let version_script_path = format!("{}.map", output_path);
+            fs::write(
+                &version_script_path,
+                format!(
+                    "{{\n    global:\n{}    local: *;\n}};\n",
+                    self.exported_functions
+                        .iter()
+                        .map(|symbol| format!("        {};\n", symbol))
+                        .collect::<String>()
+                )
+            ).unwrap();
+
+            args.push(format!("--version-script={}", version_script_path));
+        }
+
+        // The C runtime is only needed for a binary, its startup code calls 
+        // the entry point
+        let c_runtime_directory = if self.data().is_libc && !self.data().is_library {
+            let directory = self.give_c_runtime_directory();
+            if directory.is_none() {
+                self.log_c_runtime_not_found();
+            }
+            directory
+        } else {
+            None
+        };
+
+        if let Some(ref directory) = c_runtime_directory {
+            if let Some(dynamic_linker) = self.data().target.dynamic_linker() {
+                args.push("-dynamic-linker".to_string());
+                args.push(dynamic_linker.to_string());
+            }
+
+            args.push(format!("{}/crt1.o", directory));
+            args.push(format!("{}/crti.o", directory));
+            args.push(format!("-L{}", directory));
         }
 
         for source_path in &self.data.sources {
             args.push(format!("{}/{}.o", self.output_dir, source_path));
         }
 
+        if self.data().is_libc {
+            args.push("-lc".to_string());
+        }
+
+        if let Some(ref directory) = c_runtime_directory {
+            args.push(format!("{}/crtn.o", directory));
+        }
+
         platform::exec(linux_defaults::LINKER.to_string(), &args);
     }
 
@@ -346,514 +1237,505 @@ impl Compiler for LinuxCompiler {
         &mut self.stacks_data
     }
 
-    // Functions for the elements ----------------------------------------------
+    // Functions for the IR ----------------------------------------------------
 
-    fn at_assembly(&mut self, code: &Token) {
+    fn at_extern(&mut self, symbol: &str) {
+        let symbol = self.give_symbol(symbol);
         self.tools().asm_formatter.add_instruction(
-            i!(Expression(code.to_string()))
+            i!(Extern, Op::Label(symbol))
         );
     }
 
-    /// If `condition` token == `Token::None`, the condition is terminated even 
-    /// without "else" closure.
-    fn at_condition(&mut self, condition: &Token) {        
-        match condition {
-            Token::ConditionIf => {
-                self.code_data().is_condition = true;
-                self.code_data().n_condition += 1;
-
-                self.execute_next_expression();
-                
-                let instruction = i!(
-                    self.give_mnemonic_for_previous_operator(),
-                    Op::Label(format!(
-                        ".cond_{}", self.code_data().n_condition
-                    ))
-                );
-                
-                self.tools().asm_formatter.add_instruction(instruction);
-            }
-            Token::ConditionElse => {
-                let mut instructions = vec![ 
-                    i!(
-                        Jmp,
-                        Op::Label(format!(
-                            ".cond_{}", self.code_data().n_condition + 1
-                        ))
-                    ),
-                    i!(
-                        label!(format!(
-                            ".cond_{}", self.code_data().n_condition
-                        ).as_str())
-                    )
-                ];
-
-                self.code_data().n_condition += 1;
-
-                self.tools().asm_formatter.add_instructions(&mut instructions);
-                self.execute_next_expression();
-                self.code_data().is_condition = false;
-            }
-            Token::None => {
-                self.code_data().is_condition = false;
-            }
-            _ => panic!("not a condition token : {:?}", condition)
-        }
-
-        if !self.code_data().is_condition {
-            let instruction = i!(label!(
-                format!(".cond_{}", self.code_data().n_condition)
-            ));
-            self.tools().asm_formatter.add_instruction(instruction);
-        }
+    fn at_assembly(&mut self, code: &str) {
+        self.tools().asm_formatter.add_instruction(
+            i!(Expression(code.to_string()))
+        );
     }
 
+    /// When linking against the C library, the entry point is called by the C
+    /// runtime so there is no "_start" function to create. A shared or static
+    /// library has no "_start" function too, it would clash with the one of 
+    /// the program it's linked in.
+    ///
+    /// For a library, the function is declared with the function type to be
+    /// callable through the PLT, and is exported when it's public
     fn at_function(&mut self, function: &Function) {
-        if function.id().to_string() == defaults::ENTRY_POINT.to_string() {
+        if function.id == defaults::ENTRY_POINT
+            && !self.data().is_libc
+            && !self.data().is_library
+            && !self.data().is_archive
+        {
             self.create_start_function();
         }
-        
+
         // Creates a label for the function.
         //
         // Initializes the stacks.
-        self.tools().asm_formatter.add_instructions(&mut vec![
-            i!(Global, Op::Label(function.id().to_string())),
-            i!(label!(function.id())),
-            i!(Push, reg!(Rbp)),
-            i!(Mov, reg!(Rbp), reg!(Rsp)),
-        ]); 
-
-        // Retrieves the function's parameters because it's an `Element`.
-        // 
-        // The panic! will never happen.
-        let parameters = match function.params() {
-            Element::Parameters(elements) => elements,
-            _ => panic!("parameters are not a parameters element"),
-        };
-
-        // Prepares the variable stack iterator for the function 
-        self.stacks_data().i_variable_stack = 0;
-        
-        // There is no parameters to retrieve, returns
-        if parameters.is_empty() {
-            return;
-        }
+        let symbol = self.give_symbol(&function.symbol);
 
-        // Retrieves passed parameters to variables
-        let mut i_parameter: usize = 0;
-        // Creates an empty variable that will be filled step by step in the
-        // code below
-        let mut current_parameter = Variable::new(Token::None, Type::None, Token::None);
+        let global_symbol = if self.data().is_library {
+            if !function.id.starts_with(defaults::PRIVATE_PREFIX) {
+                self.exported_functions.push(symbol.clone());
+            }
+            format!("{}:function", symbol)
+        } else {
+            symbol.clone()
+        };
 
-        for element in parameters {
-            let token = match element {
-                Element::Other(token) => token,
-                _ => panic!("passed parameter is not valid : {:?}", element)
-            };
+        // The values are kept in registers when possible, the other ones have
+        // their own place in the frame
+        let registers = self.give_allocatable_registers();
+        *self.stacks_data() = CompilerStacksData::from_function(
+            function,
+            regalloc::allocate(function, &registers),
+            SLOT_SIZE,
+            linux_defaults::STACK_ALIGNMENT
+        );
 
-            let id_or_type = match token {
-                Token::TypeDef | Token::Comma => continue,
-                Token::Other(ref id_or_type) => id_or_type,
-                _ => panic!("invalid token found in parameter list : {:?}", token)
-            };
-            
-            // Sets parameter's id
-            if current_parameter.id().is_empty() {
-                current_parameter.set_id(token.clone());
-                i_parameter += 1;
-                continue;
-            }
+        let mut instructions = vec![
+            i!(Global, Op::Label(global_symbol)),
+            i!(label!(symbol)),
+            i!(Push, reg!(Rbp)),
+            i!(Mov, reg!(Rbp), reg!(Rsp)),
+        ];
 
-            current_parameter.set_type(Type::from_string(id_or_type.clone()));
-            
-            current_parameter.set_stack_pos(
-                self.stacks_data().i_variable_stack + 
-                    current_parameter.type_().to_usize()
-            );
-            // Inserts the finished variable into the stack
-            // The value is set as `Token::None` because it will be changed in
-            // Assembly code, not here
-            self.stacks_data().variable_stack.insert(
-                current_parameter.id(),
-                current_parameter.clone()
-            );
+        // Reserves the frame, so the calls do not overwrite it
+        let frame_size = self.stacks_data().frame_size;
+        if frame_size != 0 {
+            instructions.push(i!(Sub, reg!(Rsp), Op::Literal(frame_size as i64)));
+        }
 
-            let stack_position: usize = current_parameter.stack_pos();
-            self.stacks_data().i_variable_stack = stack_position;
+        // Saves the callee-saved registers used by the function
+        for (register, position) in self.stacks_data().saved_registers.clone() {
+            instructions.push(i!(Mov, give_expression_for_position(position, SLOT_SIZE), reg!(register)));
+        }
 
-            // Creates the variable associated to the parameter, in Assembly 
-            // with the passed value
-            let instruction = i!(
-                Mov,
-                self.give_expression_for_variable(&current_parameter),
-                self.give_register_for_parameter(i_parameter - 1)
-            );
+        self.tools().asm_formatter.add_instructions(&mut instructions);
 
-            self.tools().asm_formatter.add_instruction(instruction);
-
-            current_parameter = Variable::new(Token::None, Type::None, Token::None);
-        }
-    }
-
-    fn at_loop(&mut self, token: &Token) {
-        println!("{:?}", token);
-
-        match token {
-            Token::Loop => {
-                self.code_data().is_loop = true;
-                self.code_data().n_loop += 1;
-
-                let instruction = i!(
-                    label!(format!(
-                        ".loop_test_{}",
-                        self.code_data().n_loop
-                    ).as_str())
-                );
-                self.tools().asm_formatter.add_instruction(instruction);
-
-                self.execute_next_expression();
-                                
-                let mut instructions = vec![
-                    i!(
-                        self.give_mnemonic_for_previous_operator(),
-                        Op::Label(format!(
-                            ".loop_core_{}",
-                            self.code_data().n_loop
-                        ))
-                    ),
-                    i!(
-                        Jmp,
-                        Op::Label(format!(
-                            ".loop_end_{}",
-                            self.code_data().n_loop
-                        ))
-                    ),
-                    i!(
-                        label!(format!(
-                            ".loop_core_{}",
-                            self.code_data().n_loop
-                        ).as_str())
-                    )
-                ];
-
-                self.tools().asm_formatter.add_instructions(&mut instructions);   
-            }
-            Token::LoopBreak => {
-                let instruction = i!(
-                    Jmp,
-                    Op::Label(format!(
-                        ".loop_end_{}",
-                        self.code_data().n_loop
-                    ))
-                );
-                self.tools().asm_formatter.add_instruction(instruction);
-                self.code_data().is_loop = false;
-            }
-            Token::LoopContinue => {
-                let instruction = i!(
-                    Jmp,
-                    Op::Label(format!(
-                        ".loop_test_{}",
-                        self.code_data().n_loop
-                    ))
-                );
-
-                self.tools().asm_formatter.add_instruction(instruction);
-            }
-            Token::None => {
-                self.code_data().is_loop = false;
-            }
-            _ => panic!("not a loop token : {:?}", token),
+        if self.data().dumps.contains(&Dump::Stack) {
+            let layout = dump::give_stack(function, self.stacks_data(), "rbp");
+            self.code_data().current_stack_layout += &layout;
         }
 
-        if !self.code_data().is_loop {
-            let mut instructions = vec![
-                i!(
-                    Jmp,
-                    Op::Label(format!(
-                        ".loop_test_{}",
-                        self.code_data().n_loop
-                    ))
+        // Retrieves passed parameters to their locals, from the registers or
+        // from the stack
+        let types: Vec<IrType> = function.parameters
+            .iter()
+            .map(|local| function.locals[local.0].type_.clone())
+            .collect();
+        let locations = self.give_parameter_locations(&types);
+        let shadow_space = self.give_shadow_space();
+
+        for ((local, type_), location) in function.parameters.iter().zip(&types).zip(locations) {
+            let mut instructions = match location {
+                ParameterLocation::Register(register) if type_.is_float() => vec![i!(
+                    give_float_move(type_),
+                    self.give_operand_for_local(*local, 0),
+                    reg!(register)
+                )],
+                ParameterLocation::Register(register) => self.give_store(*local, 0, reg!(register)),
+                ParameterLocation::Stack(offset) => self.give_store(
+                    *local, 
+                    0, 
+                    give_expression_for_stack_parameter(shadow_space + offset)
                 ),
-                i!(
-                    label!(format!(
-                        ".loop_end_{}",
-                        self.code_data().n_loop
-                    ).as_str())
-                ),
-            ];
+            };
 
             self.tools().asm_formatter.add_instructions(&mut instructions);
         }
-    }
 
-    /// Retrieves the variable to assign and calls `self.assign_variable()` with
-    /// the value next to the operator
-    /// ```
-    /// <arg1> <operator> <arg2>
-    /// variable_to_assign = value
-    /// ```
-    fn at_assign(&mut self, operation: &Operation) {
-        let mut variable_to_assign = self.stacks_data().variable_stack
-            .get_mut(&operation.arg1().to_string())
-            .unwrap()
-            .clone();
-        
-        let arg2 = operation.arg2();
-        variable_to_assign.set_value(arg2.to_string());
-
-        if arg2 == &Token::BracketOpen {
-            self.execute_next_expression();
-        }
-    
-        self.assign_variable(&variable_to_assign);
+        self.at_blocks(function);
     }
 
-    fn at_addition(&mut self, operation: &Operation) {
-        self.do_arithmetic_operation(operation, Mnemonic::Add);
+    fn at_label(&mut self, block: BlockId) {
+        self.tools().asm_formatter.add_instruction(i!(label!(give_label(block))));
     }
-    
-    fn at_subtraction(&mut self, operation: &Operation) {
-        self.do_arithmetic_operation(operation, Mnemonic::Sub);
-    }
-    
-    fn at_multiply(&mut self, operation: &Operation) {
-        let mut instructions = vec![
-            i!(Mov, reg!(Rax), self.give_value(operation.arg1())),
-            i!(Imul, reg!(Rax), self.give_value(operation.arg2())),
-            i!(Mov, reg!(Rbx), reg!(Rax))
-        ];
+
+    fn at_copy(&mut self, dest: Temp, value: Value) {
+        let mut instructions = give_move(self.give_operand_for_temp(dest), self.give_value(value));
         self.tools().asm_formatter.add_instructions(&mut instructions);
     }
-    
-    fn at_divide(&mut self, operation: &Operation) {
-        let mut instructions = vec![
-            i!(Mov, reg!(Rax), self.give_value(operation.arg1())),
-            i!(Mov, reg!(Rdx), self.give_value(operation.arg2())),
-            i!(Idiv, reg!(Rax)),
-            i!(Mov, reg!(Rbx), reg!(Rax))
-        ];
+
+    /// The result is computed in its register, or in the scratch register when
+    /// it's in the stack.
+    ///
+    /// The quotient of a division is in "rax" and its remainder in "rdx". The
+    /// count of a shift is a constant or is in "cl".
+    ///
+    /// The unsigned operations are the same as the signed ones, except the
+    /// division and the right shift. The floating-point values are computed in
+    /// a vector register
+    fn at_binary(&mut self, dest: Temp, operator: BinaryOperator, left: Value, right: Value) {
+        let mut instructions = self.give_binary(dest, operator, left, right);
         self.tools().asm_formatter.add_instructions(&mut instructions);
     }
 
-    fn at_comparison(&mut self, operation: &Operation) {
-        let mut instructions = vec![
-            i!(Mov, reg!(Rbx), self.give_value(operation.arg1())),
-            i!(Cmp, reg!(Rbx), self.give_value(operation.arg2())),
-        ];
-        
-        let mnemonic = match operation.operator() {
-            Token::Equal => Sete,
-            Token::LessThan => Setl,
-            Token::MoreThan => Setg,
-            Token::LessThanOrEqual => Setle,
-            Token::MoreThanOrEqual => Setge, 
-            token => panic!("not a comparison operator : {:?}", token)
+    /// Two floating-point values are not equal when one of them is NaN, the
+    /// parity flag is set
+    fn at_compare(&mut self, dest: Temp, condition: Condition, left: Value, right: Value) {
+        let mut instructions = if self.is_float(left) {
+            let (mut instructions, condition) = self.give_float_comparison(condition, left, right);
+            instructions.push(i!(give_set_mnemonic(condition), reg!(Al)));
+
+            match condition {
+                Condition::Equal => instructions.append(&mut vec![
+                    i!(Setnp, reg!(R11b)),
+                    i!(And, reg!(Al), reg!(R11b)),
+                ]),
+                Condition::NotEqual => instructions.append(&mut vec![
+                    i!(Setp, reg!(R11b)),
+                    i!(Or, reg!(Al), reg!(R11b)),
+                ]),
+                _ => {}
+            }
+            instructions
+        } else {
+            let mut instructions = self.give_comparison(left, right);
+            instructions.push(i!(give_set_mnemonic(condition), reg!(Al)));
+            instructions
         };
 
-        // Actual code to retrieves the comparison value
-        if !self.code_data().is_condition && !self.code_data().is_loop {
-            instructions.push(i!(mnemonic, reg!(Al)));
-            instructions.push(i!(Movzx, reg!(defaults::RETURN_REGISTER), reg!(Al)));
-        }
+        instructions.push(i!(Movzx, reg!(Rax), reg!(Al)));
+        instructions.append(&mut give_move(self.give_operand_for_temp(dest), reg!(Rax)));
 
         self.tools().asm_formatter.add_instructions(&mut instructions);
-        self.code_data().previous_operator = operation.operator().clone();
     }
 
-    /// Moves the value to return into the default function return register
-    ///
-    /// Terminates the stacks and returns the Assembly function
-    fn at_return(&mut self, id_or_value: &Token) {
-        let instruction = if id_or_value == &Token::None {
-            i!(
-                Xor, 
-                reg!(defaults::FUN_RETURN_REGISTER), 
-                reg!(defaults::FUN_RETURN_REGISTER)
-            )
+    /// A local narrower than 64 bits in the stack is extended to the whole
+    /// register, as every temporary
+    fn at_load(&mut self, dest: Temp, local: LocalId, index: usize) {
+        let dest = self.give_operand_for_temp(dest);
+        let source = self.give_operand_for_local(local, index);
+        let type_ = self.stacks_data().local_types[local.0].clone();
+
+        let mut instructions = if is_memory(&source) && type_.size() < SLOT_SIZE {
+            let mut instructions = vec![give_extension(source, &type_)];
+            instructions.append(&mut give_move(dest, reg!(defaults::SCRATCH_REGISTER)));
+            instructions
         } else {
-            let instruction = i!(
-                Mov, 
-                reg!(defaults::FUN_RETURN_REGISTER), 
-                self.give_value(id_or_value)
-            );
-
-            instruction
+            give_move(dest, source)
         };
+        self.tools().asm_formatter.add_instructions(&mut instructions);
+    }
 
-        self.tools().asm_formatter.add_instructions(&mut vec![
-            instruction,
-            i!(Pop, reg!(Rbp)),
-            i!(Ret),
-        ]);
-
-        self.stacks_data().i_variable_stack = 0;
-        self.stacks_data().i_parameter_stack = 0;
+    fn at_store(&mut self, local: LocalId, index: usize, value: Value) {
+        let value = self.give_value(value);
+        let mut instructions = self.give_store(local, index, value);
+        self.tools().asm_formatter.add_instructions(&mut instructions);
     }
 
-    /// Sets the stack position for the variable and assign it with the value.
+    /// The value is sign-extended from the size of the type, or zero-extended
+    /// for an unsigned type, so the bits that do not fit are removed.
     ///
-    /// Pushes the variable object into the variable stack for the compiler
-    fn at_variable(&mut self, variable: &Variable) {
-        let mut variable = variable.clone();
+    /// A floating-point value converted to an integer is truncated
+    fn at_cast(&mut self, dest: Temp, value: Value, function: &Function) {
+        let dest_type = function.temps[dest.0].clone();
+        let source_type = self.give_value_type(value);
 
-        variable.set_stack_pos(
-            self.stacks_data().i_variable_stack + variable.type_().to_usize()
-        );
+        if dest_type.is_float() || source_type.is_float() {
+            let mut instructions = self.give_float_cast(dest, value, &source_type, &dest_type);
+            self.tools().asm_formatter.add_instructions(&mut instructions);
+            return;
+        }
 
-        self.stacks_data().i_variable_stack = variable.stack_pos();
-        self.stacks_data().variable_stack.insert(variable.id(), variable.clone());
+        let mut instructions = vec![
+            i!(Mov, reg!(defaults::SCRATCH_REGISTER), self.give_value(value)),
+        ];
 
-        self.assign_variable(&variable);
-    }
+        let type_ = &function.temps[dest.0];
+        if type_.size() < SLOT_SIZE {
+            instructions.push(give_extension(reg!(give_scratch_register(type_.size())), type_));
+        }
 
-    // Other functions for Assembly code ---------------------------------------
+        instructions.append(&mut give_move(
+            self.give_operand_for_temp(dest),
+            reg!(defaults::SCRATCH_REGISTER)
+        ));
+        self.tools().asm_formatter.add_instructions(&mut instructions);
+    }
 
-    fn create_start_function(&mut self) {
-        self.tools().asm_formatter.add_instructions(&mut vec![
-            i!(Global, Op::Label("_start".to_string())),
-            i!(label!("_start".to_string())),
-            i!(Call, Op::Label(defaults::ENTRY_POINT.to_string())),
-            i!(Mov, reg!(Rdi), reg!(defaults::FUN_RETURN_REGISTER)),
-            i!(Mov, reg!(Rax), Op::Literal(60)),
-            i!(Syscall),
-        ]);
+    /// The values living across the call are in callee-saved registers or in
+    /// the stack. An argument can be in the register of another parameter, so
+    /// the parameters' registers are written without overwriting an argument
+    /// not given yet.
+    ///
+    /// The parameters which are not given in registers are pushed from the
+    /// last one. The frame size is a multiple of the stack alignment, so the 
+    /// stack is aligned at the call when an even number of parameters is 
+    /// pushed, else it's padded. The caller frees the pushed parameters.
+    ///
+    /// An external function may leave garbage in the upper bits of a value 
+    /// narrower than 64 bits, so the returned value is extended. A 
+    /// floating-point value is returned in "xmm0"
+    fn at_call(&mut self, dest: Temp, callee: &Callee, arguments: &[Value], _function: &Function) {
+        let mut instructions = self.give_call(dest, callee, arguments);
+        self.tools().asm_formatter.add_instructions(&mut instructions);
     }
 
-    fn call_function(&mut self, id: &String) {
-        let parameters = match self.code_data().next_element.clone() {
-            Element::Parameters(parameters) => parameters,
-            _ => panic!("passed parameters should be a parameters element"),
-        };
+    fn at_jump(&mut self, target: BlockId) {
+        self.tools().asm_formatter.add_instruction(
+            i!(Jmp, Op::Label(give_label(target)))
+        );
+    }
 
-        let mut i_parameter: usize = 0;
+    fn at_branch(&mut self, condition: Value, then: BlockId, otherwise: BlockId, next: Option<BlockId>) {
+        let mut instructions = self.give_comparison(condition, Value::Constant(0));
+        self.tools().asm_formatter.add_instructions(&mut instructions);
 
-        for element in parameters {
-            let operand = match element {
-                Element::Expression(_) => reg!(defaults::RETURN_REGISTER),
-                Element::Other(Token::Comma) => continue,
-                Element::Other(id_or_value_or_expression) => {
-                    self.give_value(&id_or_value_or_expression)
-                },
-                _ => panic!("passed parameter is not valid : {:?}", element),
-            };
+        // Not zero is true
+        self.jump_for_condition(Condition::Equal, otherwise, then, next);
+    }
 
-            let instruction = i!(
-                Mov,
-                self.give_register_for_parameter(i_parameter),
-                operand
-            );
+    fn at_compare_branch(
+        &mut self,
+        condition: Condition,
+        left: Value,
+        right: Value,
+        then: BlockId,
+        otherwise: BlockId,
+        next: Option<BlockId>
+    ) {
+        if !self.is_float(left) {
+            let mut instructions = self.give_comparison(left, right);
+            self.tools().asm_formatter.add_instructions(&mut instructions);
 
-            self.tools().asm_formatter.add_instruction(instruction);
-            i_parameter += 1;
+            self.jump_for_condition(condition, then, otherwise, next);
+            return;
         }
 
-        self.tools().asm_formatter.add_instructions(&mut vec![
-            i!(Call, Op::Label(id.to_string())),
-            i!(
-                Mov, 
-                reg!(defaults::RETURN_REGISTER), 
-                reg!(defaults::FUN_RETURN_REGISTER)
-            )
-        ]);
-    }
+        let (mut instructions, condition) = self.give_float_comparison(condition, left, right);
 
-    fn update_return_register(&mut self, value: &Token) {
-        let instruction = i!(
-            Mov, 
-            reg!(defaults::RETURN_REGISTER), 
-            self.give_value(&value)
-        );
+        // Two floating-point values are not equal when one of them is NaN,
+        // the parity flag is set
+        match condition {
+            Condition::Equal => instructions.push(i!(Jp, Op::Label(give_label(otherwise)))),
+            Condition::NotEqual => instructions.push(i!(Jp, Op::Label(give_label(then)))),
+            _ => {}
+        }
+        self.tools().asm_formatter.add_instructions(&mut instructions);
 
-        self.tools().asm_formatter.add_instruction(instruction);
+        self.jump_for_condition(condition, then, otherwise, next);
     }
 
-    /// The value to assign is the value stored in the variable object.
+    /// Moves the value to return into the default function return register,
+    /// or into "xmm0" for a floating-point value
     ///
-    /// Detects when the value to assign is an array and calls 
-    /// `self.assign_array_variable()` instead.
-    ///
-    /// When nothing to assign, just ignore.
-    fn assign_variable(&mut self, variable: &Variable) {
-        // Detection for arrays and no value
-        match variable.value() {
-            Token::SquareBracketOpen => {
-                self.assign_array_variable(variable);
-                return;
-            },
-            Token::None => return,
-            _ => {}
+    /// Restores the saved registers, frees the frame with "leave" and returns
+    /// the Assembly function
+    fn at_return(&mut self, value: Option<Value>) {
+        let mut instructions = match value {
+            Some(value) if self.is_float(value) => {
+                let type_ = self.give_value_type(value);
+                vec![i!(give_float_move(&type_), reg!(Xmm0), self.give_float_operand(value))]
+            }
+            Some(value) => give_move(
+                reg!(defaults::FUN_RETURN_REGISTER),
+                self.give_value(value)
+            ),
+            None => vec![i!(
+                Xor,
+                reg!(defaults::FUN_RETURN_REGISTER),
+                reg!(defaults::FUN_RETURN_REGISTER)
+            )],
+        };
+
+        for (register, position) in self.stacks_data().saved_registers.clone() {
+            instructions.push(i!(Mov, reg!(register), give_expression_for_position(position, SLOT_SIZE)));
         }
 
-        let is_identifier: bool = self.before_getting_value_when_id(
-            variable.value(), 
-            defaults::RETURN_REGISTER
-        );
+        instructions.append(&mut vec![
+            i!(Leave),
+            i!(Ret),
+        ]);
+        self.tools().asm_formatter.add_instructions(&mut instructions);
+    }
 
-        let mut instruction = i!(
-            Mov,
-            self.give_expression_for_variable(variable),
-            self.give_type_operand_before_value(&variable, variable.value()),
-            {
-                if variable.value() == &Token::BracketOpen {
-                    // Here, we don't use `self.give_value()` because it also 
-                    // executes the expression and it's not required.
-                    self.execute_next_expression();
-                    Op::Expression(defaults::RETURN_REGISTER.to_string())
-                } else if is_identifier {
-                    // When the value to assign is a variable's identifier.
-                    // 
-                    // The variable's value was moved into the register, now
-                    // we move that register into the variable to assign
-                    reg!(defaults::RETURN_REGISTER)
-                } else {
-                    // `value` cannot be `Token::BracketOpen` so it's not an 
-                    // expression
-                    self.give_value(variable.value())
-                }
-            }
-        );
+    // Other functions for Assembly code ---------------------------------------
 
-        self.tools().asm_formatter.add_instruction(
-            instruction.with_comment(variable.id().to_string()).clone()
+    /// Each source file doing a checked division has its own routines, see
+    /// `create_error_routine()`
+    fn create_division_errors(&mut self) {
+        self.create_error_routine(
+            DIVISION_BY_ZERO_LABEL, 
+            DIVISION_BY_ZERO_MESSAGE_LABEL, 
+            linux_defaults::DIVISION_BY_ZERO_MESSAGE
+        );
+        self.create_error_routine(
+            DIVISION_OVERFLOW_LABEL, 
+            DIVISION_OVERFLOW_MESSAGE_LABEL, 
+            linux_defaults::DIVISION_OVERFLOW_MESSAGE
         );
     }
 
-    fn assign_array_variable(&mut self, array_variable: &Variable) {
-        let values: Vec<Token> = match self.code_data().next_element.clone() {
-            Element::Array(values) => values,
-            _ => panic!("try to assign a non-array element to an array variable"),
-        };
-
-        let (element_type, length) = match array_variable.type_() {
-            Type::Array(type_, length) => (type_.clone(), length),
-            Type::StaticArray(type_) => (type_.clone(), &0),
-            _ => panic!("assign a non-array variable"),
-        };   
-
-        for (i, value) in values.iter().enumerate() {
-            let mut value_as_variable = Variable::new(
-                Token::Other(format!("{}[{}]", array_variable.id(), i)),
-                *element_type.clone(),
-                value.clone()
-            );
+    fn create_start_function(&mut self) {
+        let entry_point = self.give_symbol(defaults::ENTRY_POINT);
 
-            value_as_variable.set_stack_pos(
-                array_variable.stack_pos() - element_type.to_usize() * i
-            );
-            
-            self.assign_variable(&value_as_variable);
-        }
+        self.tools().asm_formatter.add_instructions(&mut vec![
+            i!(Global, Op::Label("_start".to_string())),
+            i!(label!("_start".to_string())),
+            i!(Call, Op::Label(entry_point)),
+            i!(Mov, reg!(Rdi), reg!(defaults::FUN_RETURN_REGISTER)),
+            i!(Mov, reg!(Rax), Op::Literal(60)),
+            i!(Syscall),
+        ]);
     }
+}
 
-    fn give_mnemonic_for_previous_operator(&mut self) -> Mnemonic {
-        match &self.code_data().previous_operator {
-            Token::Equal => Jne,
-            Token::LessThan => Jge,
-            Token::MoreThan => Jle,
-            Token::LessThanOrEqual => Jg,
-            Token::MoreThanOrEqual => Jl, 
-            token => panic!("not a comparison operator : {:?}", token)
-        }
-    }
+/// Compiler for the x86_64 Linux target, with each temporary of its type at
+/// its location
+#[cfg(test)]
+fn give_test_compiler(temps: Vec<(IrType, Location<Register>)>, is_division_checked: bool) -> LinuxCompiler {
+    use std::collections::HashMap;
+
+    use strings::manager::StringsManager;
+
+    let data = CompilerData {
+        is_library: false,
+        is_archive: false,
+        is_libc: false,
+        is_division_checked,
+        is_optimized: false,
+        is_forced: false,
+        target: platform::Target::from_triple("x86_64-linux-gnu").unwrap(),
+        jobs: 1,
+        dumps: vec![],
+        sources: vec![],
+        signatures: HashMap::new(),
+        options: HashMap::new(),
+    };
+    let tools = CompilerTools::new(StringsManager::from_path(Path::new("../../src/strings.json")));
+
+    let mut compiler = LinuxCompiler::new((data, tools, CompilerCodeData::new(), CompilerStacksData::default()));
+    (compiler.stacks_data.temp_types, compiler.stacks_data.temps) = temps.into_iter().unzip();
+
+    compiler
+}
+
+#[test]
+fn narrow_operations() {
+    // t1: uint = shl t0, 4 ; t2: byte = add t0, 100
+    let mut compiler = give_test_compiler(vec![
+        (IrType::UnsignedInteger, Location::Register(Rbx)),
+        (IrType::UnsignedInteger, Location::Register(R12)),
+        (IrType::Byte, Location::Stack(8)),
+    ], false);
+
+    // The bits shifted beyond 32 bits are cleared
+    assert_eq!(compiler.give_binary(Temp(1), BinaryOperator::ShiftLeft, Value::Temp(Temp(0)), Value::Constant(4)), vec![
+        i!(Mov, reg!(R11), reg!(Rbx)),
+        i!(Shl, reg!(R11), Op::Literal(4)),
+        i!(Mov, reg!(R11d), reg!(R11d)),
+        i!(Mov, reg!(R12), reg!(R11)),
+    ]);
+
+    // The result wraps and is sign-extended
+    assert_eq!(compiler.give_binary(Temp(2), BinaryOperator::Add, Value::Temp(Temp(0)), Value::Constant(100)), vec![
+        i!(Mov, reg!(R11), reg!(Rbx)),
+        i!(Add, reg!(R11), Op::Literal(100)),
+        i!(Movsx, reg!(R11), reg!(R11b)),
+        i!(Mov, give_expression_for_position(8, SLOT_SIZE), reg!(R11)),
+    ]);
+}
+
+#[test]
+fn divisions() {
+    // t2: bigint = div t0, t1 ; t4: uint = umod t3, 10
+    let temps = vec![
+        (IrType::BigInteger, Location::Register(Rbx)),
+        (IrType::BigInteger, Location::Register(R12)),
+        (IrType::BigInteger, Location::Register(R13)),
+        (IrType::UnsignedInteger, Location::Register(R14)),
+        (IrType::UnsignedInteger, Location::Register(R15)),
+    ];
+    let zero_label = Op::Label(DIVISION_BY_ZERO_LABEL.to_string());
+    let overflow_label = Op::Label(DIVISION_OVERFLOW_LABEL.to_string());
+
+    let mut compiler = give_test_compiler(temps.clone(), false);
+    assert_eq!(compiler.give_binary(Temp(2), BinaryOperator::Divide, Value::Temp(Temp(0)), Value::Temp(Temp(1))), vec![
+        i!(Mov, reg!(Rax), reg!(Rbx)),
+        i!(Cqo),
+        i!(Idiv, reg!(R12)),
+        i!(Mov, reg!(R13), reg!(Rax)),
+    ]);
+
+    // Checks the divisor, then the smallest value divided by -1
+    let mut compiler = give_test_compiler(temps, true);
+    assert_eq!(compiler.give_binary(Temp(2), BinaryOperator::Divide, Value::Temp(Temp(0)), Value::Temp(Temp(1))), vec![
+        i!(Mov, reg!(Rax), reg!(Rbx)),
+        i!(Cmp, reg!(R12), Op::Literal(0)),
+        i!(Je, zero_label.clone()),
+        i!(Mov, reg!(Rdx), Op::Literal(i64::MIN)),
+        i!(Xor, reg!(Rdx), reg!(Rax)),
+        i!(Mov, reg!(Rcx), reg!(R12)),
+        i!(Add, reg!(Rcx), Op::Literal(1)),
+        i!(Or, reg!(Rdx), reg!(Rcx)),
+        i!(Je, overflow_label),
+        i!(Cqo),
+        i!(Idiv, reg!(R12)),
+        i!(Mov, reg!(R13), reg!(Rax)),
+    ]);
+
+    // An unsigned division never overflows
+    assert_eq!(compiler.give_binary(Temp(4), BinaryOperator::UnsignedModulo, Value::Temp(Temp(3)), Value::Constant(10)), vec![
+        i!(Mov, reg!(R11), Op::Literal(10)),
+        i!(Mov, reg!(Rax), reg!(R14)),
+        i!(Cmp, reg!(R11), Op::Literal(0)),
+        i!(Je, zero_label),
+        i!(Xor, reg!(Rdx), reg!(Rdx)),
+        i!(Div, reg!(R11)),
+        i!(Mov, reg!(R11), reg!(Rdx)),
+        i!(Mov, reg!(R11d), reg!(R11d)),
+        i!(Mov, reg!(R15), reg!(R11)),
+    ]);
+}
+
+#[test]
+fn calls() {
+    // t0: double = call f(1, 2, 3, 4, 5, 6, t1, 7)
+    let mut compiler = give_test_compiler(vec![
+        (IrType::Double, Location::Stack(8)),
+        (IrType::Double, Location::Stack(16)),
+        (IrType::BigInteger, Location::Register(Rbx)),
+        (IrType::BigInteger, Location::Stack(24)),
+    ], false);
+    let callee = Callee { symbol: "f".to_string(), is_extern: false };
+
+    // The seventh integer is pushed after the padding keeping the stack
+    // aligned, the floating-point value has its own register
+    let mut arguments: Vec<Value> = (1..=6).map(Value::Constant).collect();
+    arguments.append(&mut vec![Value::Temp(Temp(1)), Value::Constant(7)]);
+
+    assert_eq!(compiler.give_call(Temp(0), &callee, &arguments), vec![
+        i!(Sub, reg!(Rsp), Op::Literal(8)),
+        i!(Push, Op::Literal(7)),
+        i!(Mov, reg!(Rdi), Op::Literal(1)),
+        i!(Mov, reg!(Rsi), Op::Literal(2)),
+        i!(Mov, reg!(Rdx), Op::Literal(3)),
+        i!(Mov, reg!(Rcx), Op::Literal(4)),
+        i!(Mov, reg!(R8), Op::Literal(5)),
+        i!(Mov, reg!(R9), Op::Literal(6)),
+        i!(Movsd, reg!(Xmm0), give_expression_for_position(16, 8)),
+        i!(Call, Op::Label("f".to_string())),
+        i!(Add, reg!(Rsp), Op::Literal(16)),
+        i!(Movsd, give_expression_for_position(8, 8), reg!(Xmm0)),
+    ]);
+
+    // t2: bigint = call f(1, 2, 3, 4, 5, 6, t2, t3)
+    let mut arguments: Vec<Value> = (1..=6).map(Value::Constant).collect();
+    arguments.append(&mut vec![Value::Temp(Temp(2)), Value::Temp(Temp(3))]);
+
+    // Two pushed integers keep the stack aligned, the last one is pushed first
+    assert_eq!(compiler.give_call(Temp(2), &callee, &arguments), vec![
+        i!(Push, give_expression_for_position(24, 8)),
+        i!(Push, reg!(Rbx)),
+        i!(Mov, reg!(Rdi), Op::Literal(1)),
+        i!(Mov, reg!(Rsi), Op::Literal(2)),
+        i!(Mov, reg!(Rdx), Op::Literal(3)),
+        i!(Mov, reg!(Rcx), Op::Literal(4)),
+        i!(Mov, reg!(R8), Op::Literal(5)),
+        i!(Mov, reg!(R9), Op::Literal(6)),
+        i!(Call, Op::Label("f".to_string())),
+        i!(Add, reg!(Rsp), Op::Literal(16)),
+        i!(Mov, reg!(Rbx), reg!(Rax)),
+    ]);
 }
@@ -14,22 +14,51 @@
 use std::collections::HashMap as Dict;
 
 use jup::lang::{
+    elements::Element,
     tokens::Token,
-    elements::{ 
-        Element,
-        variable::Variable 
-    }
 };
-use x64asm::formatter::Formatter;
+use x64asm::{
+    formatter::Formatter,
+    register::Register,
+};
+use platform::Target;
 use strings::manager::StringsManager;
 
-use crate::scope::Scope;
+use crate::{
+    dump::Dump,
+    ir::{
+        regalloc::{Allocation, Location},
+        Function,
+        IrType,
+        Signature,
+    },
+};
 
 /// Some useful variables for the compiler
+#[derive(Clone)]
 pub struct CompilerData {
     pub is_library: bool,
-    
+    /// Is the output a static library ?
+    pub is_archive: bool,
+    /// Is the output linked against the C library ?
+    pub is_libc: bool,
+    /// Does a division by zero stop the program with an error ?
+    pub is_division_checked: bool,
+    /// Are the optional optimizations done ? See `ir::passes::run()`
+    pub is_optimized: bool,
+    /// Should all the source files be compiled, even the up to date ones ?
+    pub is_forced: bool,
+    pub target: Target,
+    /// Number of source files compiled at the same time
+    pub jobs: usize,
+    /// Debug dumps to print for each source file
+    pub dumps: Vec<Dump>,
+
     pub sources: Vec<String>,
+    /// Signatures of the functions of all the source files, from their 
+    /// qualified identifier. They are retrieved before compiling the source
+    /// files, see `Compiler::collect_signatures()`
+    pub signatures: Dict<String, Signature>,
     pub options: Dict<String, String>,
 }
 
@@ -39,30 +68,176 @@ pub struct CompilerTools {
     pub asm_formatter: Formatter,
 }
 
+impl CompilerTools {
+    pub fn new(strings_manager: StringsManager) -> Self {
+        Self {
+            strings_manager,
+            asm_formatter: Formatter::new(false),
+        }
+    }
+}
+
 /// Some useful variables for code walking
 pub struct CompilerCodeData {
     pub current_source: String,
+    /// Content of the source file as it was read to be parsed, hashed for the
+    /// build cache
+    pub current_content: Vec<u8>,
+    pub current_tokens: Vec<Token>,
     pub current_parsed: Vec<Element>,
-    pub scope: Scope,
-    
-    pub next_element: Element,
-    /// Should the next element be skipped ?
-    pub is_skip_next: bool,
+    /// Stack layout of each function, written only when it's dumped
+    pub current_stack_layout: String,
+}
 
-    pub is_condition: bool,
-    pub n_condition: i32,
+impl CompilerCodeData {
+    pub fn new() -> Self {
+        Self {
+            current_source: String::new(),
+            current_content: vec![],
+            current_tokens: vec![],
+            current_parsed: vec![],
+            current_stack_layout: String::new(),
+        }
+    }
+}
 
-    pub is_loop: bool,
-    pub n_loop: i32,
+/// Frame of the current function in the Assembly stack, under the base 
+/// pointer
+pub struct CompilerStacksData {
+    /// Location of each local, by `LocalId`
+    pub locals: Vec<Location<Register>>,
+    /// Type of the elements of each local, read and written with their size
+    /// when the local is in the stack
+    pub local_types: Vec<IrType>,
+    /// Location of each temporary, by `Temp`
+    pub temps: Vec<Location<Register>>,
+    /// Type of each temporary, a floating-point value is computed in the 
+    /// vector registers
+    pub temp_types: Vec<IrType>,
+    /// Callee-saved registers used by the function, with the position from
+    /// the base pointer where they are saved
+    pub saved_registers: Vec<(Register, usize)>,
+    /// Number of bytes reserved for the frame
+    pub frame_size: usize,
+}
 
-    pub previous_operator: Token,
+impl Default for CompilerStacksData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-/// Some useful variables for the Assembly stacks
-pub struct CompilerStacksData {
-    /// First one is the variable's id
-    pub variable_stack: Dict<String, Variable>,
-    pub i_variable_stack: usize,
+impl CompilerStacksData {
+    pub fn new() -> Self {
+        Self {
+            locals: vec![],
+            local_types: vec![],
+            temps: vec![],
+            temp_types: vec![],
+            saved_registers: vec![],
+            frame_size: 0,
+        }
+    }
 
-    pub i_parameter_stack: usize,
-}
\ No newline at end of file
+    /// Lays out the frame of the function from its register allocation : 
+    /// each saved register, then each local and each temporary kept in the 
+    /// stack has its own slot, aligned on its size. 
+    ///
+    /// A saved register or a temporary takes `slot_size` bytes, a local takes
+    /// the size of its type. An array has the elements' size for each 
+    /// element, its first element is at the lowest address.
+    ///
+    /// The frame size is a multiple of `alignment`, so the stack stays aligned
+    /// for the calls made by the function
+    pub fn from_function(
+        function: &Function, 
+        allocation: Allocation<Register>, 
+        slot_size: usize, 
+        alignment: usize
+    ) -> Self {
+        let mut stacks_data = Self::new();
+        let mut reserve = |size: usize, alignment: usize| {
+            stacks_data.frame_size = give_aligned(stacks_data.frame_size + size, alignment);
+            stacks_data.frame_size
+        };
+
+        let saved_registers: Vec<(Register, usize)> = allocation.saved_registers
+            .iter()
+            .map(|register| (*register, reserve(slot_size, slot_size)))
+            .collect();
+
+        let locals: Vec<Location<Register>> = allocation.locals
+            .iter()
+            .zip(&function.locals)
+            .map(|(location, local)| match location {
+                Location::Stack(_) => {
+                    let element_size = local.type_.element_type().size();
+                    Location::Stack(reserve(element_size * local.type_.length().max(1), element_size))
+                }
+                register => *register,
+            })
+            .collect();
+
+        let temps: Vec<Location<Register>> = allocation.temps
+            .iter()
+            .map(|location| match location {
+                Location::Stack(_) => Location::Stack(reserve(slot_size, slot_size)),
+                register => *register,
+            })
+            .collect();
+
+        stacks_data.saved_registers = saved_registers;
+        stacks_data.locals = locals;
+        stacks_data.local_types = function.locals
+            .iter()
+            .map(|local| local.type_.element_type().clone())
+            .collect();
+        stacks_data.temps = temps;
+        stacks_data.temp_types = function.temps.clone();
+        stacks_data.frame_size = give_aligned(stacks_data.frame_size, alignment);
+        stacks_data
+    }
+}
+
+/// Rounds up the value to a multiple of `alignment`
+fn give_aligned(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+#[test]
+fn frame_layout() {
+    use crate::ir::Local;
+
+    // let a: int ; let b: byte[3] ; let c: int ; t0: int
+    let function = Function {
+        id: "main".to_string(),
+        symbol: "main".to_string(),
+        parameters: vec![],
+        return_type: IrType::Void,
+        locals: vec![
+            Local { id: "a".to_string(), type_: IrType::Integer },
+            Local { id: "b".to_string(), type_: IrType::Array(Box::new(IrType::Byte), 3) },
+            Local { id: "c".to_string(), type_: IrType::Integer },
+        ],
+        temps: vec![IrType::Integer],
+        blocks: vec![],
+    };
+
+    // "a" is in a register, saved by the function
+    let allocation = Allocation {
+        temps: vec![Location::Stack(0)],
+        locals: vec![Location::Register(Register::Rbx), Location::Stack(0), Location::Stack(0)],
+        saved_registers: vec![Register::Rbx],
+    };
+
+    let stacks_data = CompilerStacksData::from_function(&function, allocation, 8, 16);
+
+    assert_eq!(stacks_data.saved_registers, vec![(Register::Rbx, 8)]);
+    assert_eq!(stacks_data.locals, vec![
+        Location::Register(Register::Rbx),
+        Location::Stack(11),
+        Location::Stack(16),
+    ]);
+    assert_eq!(stacks_data.temps, vec![Location::Stack(24)]);
+    assert_eq!(stacks_data.frame_size, 32);
+}
@@ -8,13 +8,55 @@ pub const ENTRY_POINT: &str = "main";
 pub const EXTENSION: &str = "ju";
 pub const EXTENSION_COMPLETE: &str = ".ju";
 pub const SCOPE_SEPARATOR: &str = ".";
+/// Imports a function from another module : `use math.square`
+pub const USE_KEYWORD: &str = "use";
+/// Declares a function defined in another object file, to type its calls :
+/// `extern fun sqrt(x: double): double`
+pub const EXTERN_KEYWORD: &str = "extern";
+/// Boolean operators, computed from left to right only until the result is
+/// known
+pub const AND_KEYWORD: &str = "and";
+pub const OR_KEYWORD: &str = "or";
+pub const NOT_KEYWORD: &str = "not";
+/// A function starting with this prefix is not exported by a library
+pub const PRIVATE_PREFIX: &str = "_";
 pub const FUN_RETURN_REGISTER: Register = Register::Rax;
-pub const RETURN_REGISTER: Register = Register::Rbx;
-pub const RETURN_REGISTER_2: Register = Register::Rdx;
+/// Registers never given to the values, used when an instruction cannot take
+/// its operands where they are
+pub const SCRATCH_REGISTER: Register = Register::R11;
+pub const SCRATCH_REGISTER_2: Register = Register::Rax;
 
 pub mod linux_defaults {
     pub const ASSEMBLER: &str = "nasm";
     pub const LINKER: &str = "ld";
 
     pub const OUTPUT_FILE: &str = "junon.out";
+
+    /// The stack pointer is aligned on this number of bytes before each call,
+    /// as required by the System V ABI
+    pub const STACK_ALIGNMENT: usize = 16;
+
+    /// Written to the error output on a division by zero, with
+    /// "--check-division"
+    pub const DIVISION_BY_ZERO_MESSAGE: &str = "error: division by zero";
+    /// Written to the error output on a division of the smallest value of a
+    /// signed type by -1, with "--check-division"
+    pub const DIVISION_OVERFLOW_MESSAGE: &str = "error: division overflow";
+    /// Exit code of a program stopped by a division error, the one of a 
+    /// program killed by "SIGFPE"
+    pub const DIVISION_ERROR_EXIT_CODE: i64 = 136;
+
+    /// Directories where the C runtime object files ("crt1.o", "crti.o" and 
+    /// "crtn.o") of the GNU C library are searched, in this order
+    pub const GNU_C_RUNTIME_DIRECTORIES: [&str; 3] = [
+        "/usr/lib/x86_64-linux-gnu",
+        "/usr/lib64",
+        "/usr/lib",
+    ];
+    /// Same for the musl C library, installed apart from the system's one
+    pub const MUSL_C_RUNTIME_DIRECTORIES: [&str; 3] = [
+        "/usr/lib/x86_64-linux-musl",
+        "/usr/lib/musl/lib",
+        "/usr/local/musl/lib",
+    ];
 }
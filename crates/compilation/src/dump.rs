@@ -0,0 +1,200 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Debug dumps of what the compiler understood from a source file, asked with
+//! `--dump` : `juc main.ju --dump tokens,ir`
+//!
+//! Each dump is printed at once with a header naming the source file, so the
+//! dumps of source files compiled at the same time are not mixed
+
+use jup::lang::{
+    elements::Element,
+    tokens::Token,
+};
+use x64asm::register::Register;
+
+use crate::{
+    data::CompilerStacksData,
+    defaults,
+    ir::{
+        regalloc::Location,
+        Function,
+        Module,
+    },
+};
+
+/// All the available dumps, as written after `--dump`
+pub const DUMPS: [&str; 5] = [
+    "tokens",
+    "elements",
+    "scopes",
+    "stack",
+    "ir",
+];
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Dump {
+    /// Output of the tokenizer
+    Tokens,
+    /// Output of the parser
+    Elements,
+    /// Modules, functions, locals and imports once resolved
+    Scopes,
+    /// Place of each local and temporary in the stack of each function
+    Stack,
+    /// IR of the source file after the passes
+    Ir,
+}
+
+impl Dump {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "tokens" => Some(Self::Tokens),
+            "elements" => Some(Self::Elements),
+            "scopes" => Some(Self::Scopes),
+            "stack" => Some(Self::Stack),
+            "ir" => Some(Self::Ir),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Tokens => "tokens",
+            Self::Elements => "elements",
+            Self::Scopes => "scopes",
+            Self::Stack => "stack",
+            Self::Ir => "ir",
+        }
+    }
+}
+
+/// Retrieves the dumps from the value given to `--dump`, separated by commas.
+///
+/// Returns the invalid identifier as error
+pub fn from_request(request: &str) -> Result<Vec<Dump>, String> {
+    let mut dumps: Vec<Dump> = vec![];
+
+    for id in request.split(',').map(|id| id.trim()) {
+        match Dump::from_id(id) {
+            Some(dump) if !dumps.contains(&dump) => dumps.push(dump),
+            Some(_) => {}
+            None => return Err(id.to_string()),
+        }
+    }
+
+    Ok(dumps)
+}
+
+/// Prints the content of a dump for a source file
+pub fn print(dump: Dump, source_path: &str, content: &str) {
+    println!(
+        "\x1b[1m-- {} : {} --\x1b[0m\n{}",
+        dump.id(),
+        source_path,
+        content.trim_end()
+    );
+}
+
+/// One line of tokens for each line of the source file
+pub fn give_tokens(tokens: &[Token]) -> String {
+    let mut to_write = String::new();
+
+    for (i_line, line) in tokens.split(|token| *token == Token::NewLine).enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+
+        let line: Vec<String> = line.iter()
+            .map(|token| format!("{:?}", token))
+            .collect();
+
+        to_write += &format!("{:>4} | {}\n", i_line + 1, line.join(" "));
+    }
+
+    to_write
+}
+
+pub fn give_elements(elements: &[Element]) -> String {
+    let mut to_write = String::new();
+
+    for element in elements {
+        to_write += &format!("{:#?}\n", element);
+    }
+
+    to_write
+}
+
+/// The functions of the module with their symbol, their locals, then what the
+/// module takes from the other ones
+pub fn give_scopes(module: &Module) -> String {
+    let mut to_write = format!("module {}\n", module.id);
+
+    for function in &module.functions {
+        to_write += &format!(
+            "  {}{}{} -> {}\n",
+            module.id,
+            defaults::SCOPE_SEPARATOR,
+            function.id,
+            function.symbol
+        );
+
+        for local in &function.locals {
+            to_write += &format!(
+                "    {}{}{}{}{}: {}\n",
+                module.id,
+                defaults::SCOPE_SEPARATOR,
+                function.id,
+                defaults::SCOPE_SEPARATOR,
+                local.id,
+                local.type_
+            );
+        }
+    }
+
+    for (id, qualified_id) in &module.imports {
+        to_write += &format!("  {} {} -> {}\n", defaults::USE_KEYWORD, id, qualified_id);
+    }
+
+    for symbol in &module.externs {
+        to_write += &format!("  extern {}\n", symbol);
+    }
+
+    to_write
+}
+
+/// Place of the locals and temporaries of the function from its stacks data :
+/// a register, or an offset from the base pointer `base`
+pub fn give_stack(function: &Function, stacks_data: &CompilerStacksData, base: &str) -> String {
+    let give_location = |location: &Location<Register>| match location {
+        Location::Register(register) => register.to_string(),
+        Location::Stack(position) => format!("[{}-{}]", base, position),
+    };
+
+    let mut to_write = format!("{} ({} bytes)\n", function.symbol, stacks_data.frame_size);
+
+    for (register, position) in &stacks_data.saved_registers {
+        to_write += &format!("  [{}-{}] saved {}\n", base, position, register.to_string());
+    }
+
+    for (local, location) in function.locals.iter().zip(&stacks_data.locals) {
+        to_write += &format!("  {} {}: {}\n", give_location(location), local.id, local.type_);
+    }
+
+    for (i_temp, (type_, location)) in function.temps.iter()
+        .zip(&stacks_data.temps)
+        .enumerate()
+    {
+        to_write += &format!("  {} t{}: {}\n", give_location(location), i_temp, type_);
+    }
+
+    to_write
+}
+
+#[test]
+fn dump_request() {
+    assert_eq!(from_request("tokens"), Ok(vec![Dump::Tokens]));
+    assert_eq!(from_request("ir, stack,ir"), Ok(vec![Dump::Ir, Dump::Stack]));
+    assert_eq!(from_request("tokens,types"), Err("types".to_string()));
+}
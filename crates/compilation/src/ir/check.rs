@@ -0,0 +1,575 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Type checking of the IR, once the constants are folded.
+//!
+//! A value can be given to a wider number type (`byte` to `int`, `int` to
+//! `bigint`), a narrowing conversion needs an explicit cast written like a
+//! call : `byte(a)`. A signed value is never implicitly given to an unsigned
+//! type, or the reverse, and they cannot be operands of the same operation.
+//! The same goes for the integers and the floating-point values, a float is
+//! only given to a double without cast.
+//! A constant has to fit in the type it's given to, or in the type of its
+//! operation. The calls to the functions of the module are checked against
+//! their signature
+
+use rslog::{
+    level::LogLevel,
+    log::Log,
+};
+use strings::{
+    manager::StringsManager,
+    structured::MultiString,
+};
+
+use crate::ir::{
+    BinaryOperator,
+    Function,
+    Instruction,
+    IrType,
+    Module,
+    Temp,
+    Terminator,
+    Value,
+};
+
+/// Type error found in the function `function`, identified as written in the
+/// source file, at the line `line` of the source file. The line is zero when
+/// it's not known
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum TypeError {
+    /// The local `local` of type `expected` receives a value of type `found`
+    Mismatch { function: String, line: usize, local: String, expected: IrType, found: IrType },
+    /// The parameter `parameter` of `callee` receives a value of another type
+    ArgumentMismatch {
+        function: String,
+        line: usize,
+        callee: String,
+        parameter: String,
+        expected: IrType,
+        found: IrType,
+    },
+    /// The returned value is not of the function's return type
+    ReturnMismatch { function: String, line: usize, expected: IrType, found: IrType },
+    /// The operands of an operation are a signed and an unsigned value, or an
+    /// integer and a floating-point value
+    OperandsMismatch { function: String, line: usize, left: IrType, right: IrType },
+    /// The constant does not fit in the type it's given to
+    OutOfRange { function: String, line: usize, value: i64, type_: IrType },
+    ArgumentsCount { function: String, line: usize, callee: String, expected: usize, found: usize },
+    /// The function ends without returning a value of its return type
+    MissingReturnValue { function: String, line: usize, type_: IrType },
+    /// The function returns a value without having a return type
+    UnexpectedReturnValue { function: String, line: usize },
+    /// The value of `callee` is used, but it returns nothing
+    NoValue { function: String, line: usize, callee: String },
+    /// The type `type_` written in the source file does not exist, found while
+    /// lowering the module
+    UnknownType { function: String, line: usize, type_: String },
+}
+
+/// Checks all the functions of the module
+pub fn check_module(module: &Module) -> Vec<TypeError> {
+    let mut errors: Vec<TypeError> = vec![];
+
+    for function in &module.functions {
+        let mut checker = FunctionChecker {
+            module,
+            function,
+            line: 0,
+            errors: vec![],
+        };
+
+        checker.check();
+        errors.append(&mut checker.errors);
+    }
+
+    errors
+}
+
+struct FunctionChecker<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    /// Line of the checked instruction, from the last `Instruction::Line`
+    line: usize,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> FunctionChecker<'a> {
+    fn check(&mut self) {
+        for block in &self.function.blocks {
+            for instruction in &block.instructions {
+                self.check_instruction(instruction);
+            }
+
+            self.check_terminator(&block.terminator);
+        }
+    }
+
+    fn check_instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Line(line) => self.line = *line,
+            Instruction::Store { local, value, .. } => {
+                let local = &self.function.locals[local.0];
+
+                self.check_value(*value, local.type_.element_type(), |function, line, found| {
+                    TypeError::Mismatch {
+                        function,
+                        line,
+                        local: local.id.clone(),
+                        expected: local.type_.element_type().clone(),
+                        found,
+                    }
+                });
+            }
+            Instruction::Call { callee, arguments, .. } => {
+                let called = match callee.is_extern {
+                    false => self.module.functions
+                        .iter()
+                        .find(|function| function.symbol == callee.symbol)
+                        .map(Function::signature),
+                    true => self.module.signatures
+                        .iter()
+                        .find(|(symbol, _signature)| symbol == &callee.symbol)
+                        .map(|(_symbol, signature)| signature.clone()),
+                };
+
+                // The signature of an external function is known only when
+                // it's declared
+                let called = match called {
+                    Some(called) => called,
+                    None => {
+                        for argument in arguments {
+                            self.check_has_value(*argument);
+                        }
+                        return;
+                    }
+                };
+
+                if arguments.len() != called.parameters.len() {
+                    self.errors.push(TypeError::ArgumentsCount {
+                        function: self.function.id.clone(),
+                        line: self.line,
+                        callee: called.id.clone(),
+                        expected: called.parameters.len(),
+                        found: arguments.len(),
+                    });
+                    return;
+                }
+
+                for (argument, parameter) in arguments.iter().zip(&called.parameters) {
+                    self.check_value(*argument, &parameter.type_, |function, line, found| {
+                        TypeError::ArgumentMismatch {
+                            function,
+                            line,
+                            callee: called.id.clone(),
+                            parameter: parameter.id.clone(),
+                            expected: parameter.type_.clone(),
+                            found,
+                        }
+                    });
+                }
+            }
+            // The count of a shift can be of any sign
+            Instruction::Binary {
+                operator: BinaryOperator::ShiftLeft
+                    | BinaryOperator::ShiftRight
+                    | BinaryOperator::UnsignedShiftRight,
+                ..
+            } => {
+                for value in instruction.values() {
+                    self.check_has_value(value);
+                }
+            }
+            Instruction::Binary { left, right, .. } | Instruction::Compare { left, right, .. } => {
+                if !self.check_has_value(*left) || !self.check_has_value(*right) {
+                    return;
+                }
+
+                let (left, right) = match (left, right) {
+                    (Value::Temp(left), Value::Temp(right)) => (left, right),
+                    (Value::Constant(constant), Value::Temp(_)) | (Value::Temp(_), Value::Constant(constant)) => {
+                        // A constant operand has to fit in the type of the
+                        // operation, the one of the other operand for a
+                        // comparison
+                        let temps = &self.function.temps;
+                        let type_ = match instruction {
+                            Instruction::Binary { dest, .. } => temps[dest.0].clone(),
+                            _ => match (left, right) {
+                                (Value::Temp(temp), _) | (_, Value::Temp(temp)) => temps[temp.0].clone(),
+                                _ => unreachable!(),
+                            },
+                        };
+                        let is_mask = matches!(
+                            instruction,
+                            Instruction::Binary {
+                                operator: BinaryOperator::BitwiseAnd
+                                    | BinaryOperator::BitwiseOr
+                                    | BinaryOperator::BitwiseXor,
+                                ..
+                            }
+                        );
+
+                        self.check_range(*constant, &type_, is_mask);
+                        return;
+                    }
+                    _ => return,
+                };
+
+                let left = &self.function.temps[left.0];
+                let right = &self.function.temps[right.0];
+
+                if left.is_unsigned() != right.is_unsigned() || left.is_float() != right.is_float() {
+                    self.errors.push(TypeError::OperandsMismatch {
+                        function: self.function.id.clone(),
+                        line: self.line,
+                        left: left.clone(),
+                        right: right.clone(),
+                    });
+                }
+            }
+            instruction => {
+                for value in instruction.values() {
+                    self.check_has_value(value);
+                }
+            }
+        }
+    }
+
+    fn check_terminator(&mut self, terminator: &Terminator) {
+        let return_type = &self.function.return_type;
+
+        match terminator {
+            Terminator::Return(Some(value)) => {
+                if return_type == &IrType::Void {
+                    self.errors.push(TypeError::UnexpectedReturnValue {
+                        function: self.function.id.clone(),
+                        line: self.line,
+                    });
+                    return;
+                }
+
+                self.check_value(*value, return_type, |function, line, found| {
+                    TypeError::ReturnMismatch {
+                        function,
+                        line,
+                        expected: return_type.clone(),
+                        found,
+                    }
+                });
+            }
+            Terminator::Return(None) => {
+                if return_type != &IrType::Void {
+                    self.errors.push(TypeError::MissingReturnValue {
+                        function: self.function.id.clone(),
+                        line: self.line,
+                        type_: return_type.clone(),
+                    });
+                }
+            }
+            Terminator::Branch { condition, .. } => {
+                self.check_has_value(*condition);
+            }
+            Terminator::Jump(_) => {}
+        }
+    }
+
+    /// Checks that the value can be given to the type `expected`, `mismatch`
+    /// creates the error from the function's identifier and the value's type
+    fn check_value<F>(&mut self, value: Value, expected: &IrType, mismatch: F)
+    where
+        F: FnOnce(String, usize, IrType) -> TypeError
+    {
+        match value {
+            // An integer constant given to a floating-point type is converted
+            // by the lowering
+            Value::Constant(_constant) if expected.is_float() => {
+                self.errors.push(mismatch(self.function.id.clone(), self.line, IrType::BigInteger));
+            }
+            Value::Constant(constant) => self.check_range(constant, expected, false),
+            Value::Temp(temp) => {
+                if !self.check_has_value(value) {
+                    return;
+                }
+
+                let found = &self.function.temps[temp.0];
+                if !expected.can_hold(found) {
+                    self.errors.push(mismatch(self.function.id.clone(), self.line, found.clone()));
+                }
+            }
+        }
+    }
+
+    /// Checks that the constant fits in the number type. The mask of a bitwise
+    /// operation can be written with either sign
+    fn check_range(&mut self, constant: i64, type_: &IrType, is_mask: bool) {
+        let (min, max) = match type_.range() {
+            Some(_range) if is_mask => {
+                let bits = type_.size() * 8;
+                (-(1 << (bits - 1)), (1 << bits) - 1)
+            }
+            Some(range) => range,
+            None => return,
+        };
+        if !(min..=max).contains(&(constant as i128)) {
+            self.errors.push(TypeError::OutOfRange {
+                function: self.function.id.clone(),
+                line: self.line,
+                value: constant,
+                type_: type_.clone(),
+            });
+        }
+    }
+
+    /// Returns false when the value is the result of a function returning
+    /// nothing
+    fn check_has_value(&mut self, value: Value) -> bool {
+        let temp = match value {
+            Value::Temp(temp) if self.function.temps[temp.0] == IrType::Void => temp,
+            _ => return true,
+        };
+
+        self.errors.push(TypeError::NoValue {
+            function: self.function.id.clone(),
+            line: self.line,
+            callee: self.give_callee_id(temp),
+        });
+
+        false
+    }
+
+    /// Gives the identifier of the function called to compute the temporary
+    fn give_callee_id(&self, temp: Temp) -> String {
+        let symbol = self.function.blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .find_map(|instruction| match instruction {
+                Instruction::Call { dest, callee, .. } if *dest == temp => Some(&callee.symbol),
+                _ => None,
+            })
+            .unwrap();
+
+        match self.module.functions.iter().find(|function| &function.symbol == symbol) {
+            Some(function) => function.id.clone(),
+            None => symbol.clone(),
+        }
+    }
+}
+
+impl TypeError {
+    pub fn line(&self) -> usize {
+        match self {
+            TypeError::Mismatch { line, .. }
+            | TypeError::ArgumentMismatch { line, .. }
+            | TypeError::ReturnMismatch { line, .. }
+            | TypeError::OperandsMismatch { line, .. }
+            | TypeError::OutOfRange { line, .. }
+            | TypeError::ArgumentsCount { line, .. }
+            | TypeError::MissingReturnValue { line, .. }
+            | TypeError::UnexpectedReturnValue { line, .. }
+            | TypeError::NoValue { line, .. }
+            | TypeError::UnknownType { line, .. } => *line,
+        }
+    }
+
+    /// Creates the error log to show, in the speak language. The message
+    /// starts with the position of the error : "src/math.ju:3"
+    pub fn to_log(&self, sm: &StringsManager, source_path: &str) -> Log {
+        let errors = &sm.get().logs.errors;
+
+        // Replaces each "{}" of a string by the next argument
+        let format = |string: &Option<MultiString>, arguments: &[&str]| {
+            let mut string = string.as_ref().unwrap().get(sm);
+            for argument in arguments {
+                string = string.replacen("{}", argument, 1);
+            }
+            string
+        };
+
+        let (strings, message, hint) = match self {
+            TypeError::Mismatch { function, local, expected, found, .. } => (
+                &errors.type_mismatch,
+                format(&errors.type_mismatch.message, &[
+                    function, local, &expected.to_string(), &found.to_string()
+                ]),
+                Some(format(&errors.type_mismatch.hint, &[&expected.to_string()])),
+            ),
+            TypeError::ArgumentMismatch { function, callee, parameter, expected, found, .. } => (
+                &errors.argument_type_mismatch,
+                format(&errors.argument_type_mismatch.message, &[
+                    function, parameter, callee, &expected.to_string(), &found.to_string()
+                ]),
+                Some(format(&errors.argument_type_mismatch.hint, &[&expected.to_string()])),
+            ),
+            TypeError::ReturnMismatch { function, expected, found, .. } => (
+                &errors.return_type_mismatch,
+                format(&errors.return_type_mismatch.message, &[
+                    function, &found.to_string(), &expected.to_string()
+                ]),
+                Some(format(&errors.return_type_mismatch.hint, &[&expected.to_string()])),
+            ),
+            TypeError::OperandsMismatch { function, left, right, .. } => (
+                &errors.operands_type_mismatch,
+                format(&errors.operands_type_mismatch.message, &[
+                    function, &left.to_string(), &right.to_string()
+                ]),
+                Some(format(&errors.operands_type_mismatch.hint, &[&left.to_string()])),
+            ),
+            TypeError::OutOfRange { function, value, type_, .. } => {
+                let (min, max) = type_.range().unwrap();
+
+                (
+                    &errors.value_out_of_range,
+                    format(&errors.value_out_of_range.message, &[
+                        function, &value.to_string(), &type_.to_string()
+                    ]),
+                    Some(format(&errors.value_out_of_range.hint, &[
+                        &type_.to_string(), &min.to_string(), &max.to_string()
+                    ])),
+                )
+            }
+            TypeError::ArgumentsCount { function, callee, expected, found, .. } => (
+                &errors.arguments_count,
+                format(&errors.arguments_count.message, &[
+                    function, callee, &expected.to_string(), &found.to_string()
+                ]),
+                None,
+            ),
+            TypeError::MissingReturnValue { function, type_, .. } => (
+                &errors.missing_return_value,
+                format(&errors.missing_return_value.message, &[function, &type_.to_string()]),
+                None,
+            ),
+            TypeError::UnexpectedReturnValue { function, .. } => (
+                &errors.unexpected_return_value,
+                format(&errors.unexpected_return_value.message, &[function]),
+                Some(format(&errors.unexpected_return_value.hint, &[function])),
+            ),
+            TypeError::NoValue { function, callee, .. } => (
+                &errors.no_return_value,
+                format(&errors.no_return_value.message, &[function, callee]),
+                None,
+            ),
+            TypeError::UnknownType { function, type_, .. } => (
+                &errors.unknown_type,
+                format(&errors.unknown_type.message, &[function, type_]),
+                Some(format(&errors.unknown_type.hint, &[])),
+            ),
+        };
+
+        let message = match self.line() {
+            0 => format!("{} : {}", source_path, message),
+            line => format!("{}:{} : {}", source_path, line, message),
+        };
+        let log = Log::new(LogLevel::Error, format(&strings.title, &[]), message);
+
+        match hint {
+            Some(hint) => log.add_hint(hint),
+            None => log,
+        }
+    }
+}
+
+#[test]
+fn type_checking() {
+    use crate::ir::{Block, BlockId, Condition, Local, LocalId};
+
+    // fun main(): byte {
+    //     let a: byte = 300
+    //     let b: byte = t0 (int)
+    //     t0 (int) < t1 (uint)
+    //     t2 (byte) + 300
+    //     t2 (byte) ^ -1
+    //     ret
+    // }
+    let function = Function {
+        id: "main".to_string(),
+        symbol: "main".to_string(),
+        parameters: vec![],
+        return_type: IrType::Byte,
+        locals: vec![
+            Local { id: "a".to_string(), type_: IrType::Byte },
+            Local { id: "b".to_string(), type_: IrType::Byte },
+        ],
+        temps: vec![IrType::Integer, IrType::UnsignedInteger, IrType::Byte, IrType::Byte, IrType::Byte],
+        blocks: vec![
+            Block {
+                id: BlockId(0),
+                instructions: vec![
+                    Instruction::Line(2),
+                    Instruction::Store { local: LocalId(0), index: 0, value: Value::Constant(300) },
+                    Instruction::Line(3),
+                    Instruction::Copy { dest: Temp(0), value: Value::Constant(1) },
+                    Instruction::Store { local: LocalId(1), index: 0, value: Value::Temp(Temp(0)) },
+                    Instruction::Line(4),
+                    Instruction::Copy { dest: Temp(1), value: Value::Constant(2) },
+                    Instruction::Compare {
+                        dest: Temp(2),
+                        condition: Condition::LessThan,
+                        left: Value::Temp(Temp(0)),
+                        right: Value::Temp(Temp(1)),
+                    },
+                    Instruction::Line(5),
+                    Instruction::Binary {
+                        dest: Temp(3),
+                        operator: BinaryOperator::Add,
+                        left: Value::Temp(Temp(2)),
+                        right: Value::Constant(300),
+                    },
+                    Instruction::Line(6),
+                    Instruction::Binary {
+                        dest: Temp(4),
+                        operator: BinaryOperator::BitwiseXor,
+                        left: Value::Temp(Temp(2)),
+                        right: Value::Constant(-1),
+                    },
+                    Instruction::Line(7),
+                ],
+                terminator: Terminator::Return(None),
+            },
+        ],
+    };
+
+    let module = Module {
+        id: "main".to_string(),
+        functions: vec![function],
+        externs: vec![],
+        signatures: vec![],
+        imports: vec![],
+        assembly: vec![],
+    };
+
+    assert_eq!(check_module(&module), vec![
+        TypeError::OutOfRange {
+            function: "main".to_string(),
+            line: 2,
+            value: 300,
+            type_: IrType::Byte,
+        },
+        TypeError::Mismatch {
+            function: "main".to_string(),
+            line: 3,
+            local: "b".to_string(),
+            expected: IrType::Byte,
+            found: IrType::Integer,
+        },
+        TypeError::OperandsMismatch {
+            function: "main".to_string(),
+            line: 4,
+            left: IrType::Integer,
+            right: IrType::UnsignedInteger,
+        },
+        TypeError::OutOfRange {
+            function: "main".to_string(),
+            line: 5,
+            value: 300,
+            type_: IrType::Byte,
+        },
+        TypeError::MissingReturnValue {
+            function: "main".to_string(),
+            line: 7,
+            type_: IrType::Byte,
+        },
+    ]);
+}
@@ -0,0 +1,382 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Expressions of any depth, parsed by precedence climbing.
+//!
+//! The parser gives the operations of a line as elements. They are written
+//! back as a list of items in the order of the source file, then parsed to a
+//! tree with the precedence and the associativity of each operator :
+//! `{ a + b * c - 2 }` is `(a + (b * c)) - 2`, `-(a + 1) * 2` is
+//! `(-(a + 1)) * 2`.
+//!
+//! A `{ }` block in an expression can declare variables, it's kept as a block
+//! and its value is the value of its last element
+
+use jup::lang::{
+    elements::Element,
+    tokens::Token,
+};
+
+use crate::{
+    defaults,
+    ir::lower::KindToken,
+};
+
+/// Binding power of the unary minus, stronger than all binary operators
+const PREFIX_BINDING_POWER: u8 = 100;
+/// Binding power of "not", weaker than the comparisons : `not a == b` is
+/// `not (a == b)`
+const NOT_BINDING_POWER: u8 = 6;
+
+/// Operators given as other tokens by the tokenizer
+pub const NOT_EQUAL: &str = "!=";
+pub const MODULO: &str = "%";
+pub const BITWISE_AND: &str = "&";
+pub const BITWISE_OR: &str = "|";
+pub const BITWISE_XOR: &str = "^";
+pub const BITWISE_NOT: &str = "~";
+pub const SHIFT_LEFT: &str = "<<";
+pub const SHIFT_RIGHT: &str = ">>";
+
+/// Element of an expression, once written back in the source file's order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// Identifier, value or operator
+    Token(Token),
+    /// Parentheses
+    Group(Vec<Item>),
+    /// `{ }` block
+    Block(Vec<Element>),
+    /// Passed parameters of a call, after the called identifier
+    Arguments(Vec<Vec<Item>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// Number or character
+    Value(Token),
+    Variable(String),
+    Block(Vec<Element>),
+    Call { id: String, arguments: Vec<Expression> },
+    Unary { operator: Token, operand: Box<Expression> },
+    Binary { operator: Token, left: Box<Expression>, right: Box<Expression> },
+}
+
+/// Gives the binding powers of a binary operator, on its left and on its
+/// right. The left one is lower for a left associative operator.
+///
+/// The bitwise operators are done before the comparisons : `a & 1 == 0` is
+/// `(a & 1) == 0`
+pub fn give_binding_power(operator: &Token) -> Option<(u8, u8)> {
+    match operator {
+        Token::Equal
+        | Token::LessThan
+        | Token::MoreThan
+        | Token::LessThanOrEqual
+        | Token::MoreThanOrEqual => Some((10, 11)),
+        Token::Plus | Token::Minus => Some((20, 21)),
+        Token::Multiply | Token::Divide => Some((30, 31)),
+        Token::Other(operator) => match operator.as_str() {
+            defaults::OR_KEYWORD => Some((2, 3)),
+            defaults::AND_KEYWORD => Some((4, 5)),
+            NOT_EQUAL => Some((10, 11)),
+            BITWISE_OR => Some((12, 13)),
+            BITWISE_XOR => Some((14, 15)),
+            BITWISE_AND => Some((16, 17)),
+            SHIFT_LEFT | SHIFT_RIGHT => Some((18, 19)),
+            MODULO => Some((30, 31)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Gives the operator of a compound assignment like `a += 1`, which is
+/// `a = { a + 1 }`
+pub fn give_compound_operator(assignment: &Token) -> Option<Token> {
+    let operator = match assignment {
+        Token::Other(assignment) => assignment.strip_suffix('=')?,
+        _ => return None,
+    };
+
+    match operator {
+        "+" => Some(Token::Plus),
+        "-" => Some(Token::Minus),
+        "*" => Some(Token::Multiply),
+        "/" => Some(Token::Divide),
+        MODULO | BITWISE_AND | BITWISE_OR | BITWISE_XOR | SHIFT_LEFT | SHIFT_RIGHT => {
+            Some(Token::Other(operator.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Can the element be the first one of an expression ? A `{ }` block alone is
+/// not, it's lowered as a block
+pub fn is_expression_start(element: &Element) -> bool {
+    match element {
+        Element::Operation(operation) => operation.operator() != &Token::Assign,
+        Element::Other(Token::Other(id_or_value)) => !is_keyword(id_or_value),
+        Element::Other(Token::Minus) => true,
+        _ => false,
+    }
+}
+
+/// Is the identifier a keyword starting a declaration, not an expression ?
+fn is_keyword(id: &str) -> bool {
+    id == defaults::USE_KEYWORD || id == defaults::EXTERN_KEYWORD
+}
+
+/// Writes back the elements of an expression as items, until the end of the
+/// line or an element which is not part of an expression.
+///
+/// Returns the items and the number of elements taken
+pub fn flatten(elements: &[Element]) -> (Vec<Item>, usize) {
+    let mut items: Vec<Item> = vec![];
+    let mut i = 0;
+
+    while i < elements.len() {
+        match &elements[i] {
+            Element::Operation(operation) if operation.operator() != &Token::Assign => {
+                for token in [operation.arg1(), operation.operator(), operation.arg2()] {
+                    // The expression of an operand is the next element
+                    if token == &Token::BracketOpen {
+                        i += 1;
+                        match elements.get(i) {
+                            Some(Element::Expression(block)) => items.push(Item::Block(block.clone())),
+                            element => panic!("expected an expression, found : {:?}", element),
+                        }
+                    } else {
+                        items.push(Item::Token(token.clone()));
+                    }
+                }
+            }
+            Element::Other(token @ Token::Other(id_or_value)) if !is_keyword(id_or_value) => {
+                items.push(Item::Token(token.clone()));
+            }
+            Element::Other(token) if give_binding_power(token).is_some() => {
+                items.push(Item::Token(token.clone()));
+            }
+            Element::Parameters(parameters) => {
+                let is_call = matches!(
+                    items.last(),
+                    Some(Item::Token(Token::Other(id))) if KindToken::from_token(&Token::Other(id.clone())) == KindToken::Identifier
+                );
+
+                if is_call {
+                    let arguments = parameters
+                        .split(|parameter| parameter == &Element::Other(Token::Comma))
+                        .filter(|argument| !argument.is_empty())
+                        .map(|argument| flatten(argument).0)
+                        .collect();
+
+                    items.push(Item::Arguments(arguments));
+                } else {
+                    items.push(Item::Group(flatten(parameters).0));
+                }
+            }
+            // A block is an operand after an operator, or alone
+            Element::Expression(block) if is_operand_expected(&items) => {
+                items.push(Item::Block(block.clone()));
+            }
+            _ => break,
+        }
+
+        i += 1;
+    }
+
+    (items, i)
+}
+
+fn is_operand_expected(items: &[Item]) -> bool {
+    match items.last() {
+        None => true,
+        Some(Item::Token(token)) => give_binding_power(token).is_some(),
+        Some(_) => false,
+    }
+}
+
+/// Parses the items to an expression tree
+pub fn parse(items: &[Item]) -> Expression {
+    let mut parser = ExpressionParser { items, position: 0 };
+    let expression = parser.parse_expression(0);
+
+    if parser.position != items.len() {
+        panic!("unexpected item in expression : {:?}", items[parser.position]);
+    }
+
+    expression
+}
+
+struct ExpressionParser<'a> {
+    items: &'a [Item],
+    position: usize,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn next(&mut self) -> Option<&'a Item> {
+        let item = self.items.get(self.position);
+        self.position += 1;
+        item
+    }
+
+    fn peek(&self) -> Option<&'a Item> {
+        self.items.get(self.position)
+    }
+
+    /// Parses while the operators bind stronger than `min_binding_power`
+    fn parse_expression(&mut self, min_binding_power: u8) -> Expression {
+        let mut left = self.parse_operand();
+
+        while let Some(Item::Token(operator)) = self.peek() {
+            let (left_power, right_power) = match give_binding_power(operator) {
+                Some(binding_power) => binding_power,
+                None => panic!("expected an operator, found : {:?}", operator),
+            };
+
+            if left_power < min_binding_power {
+                break;
+            }
+            self.position += 1;
+
+            let right = self.parse_expression(right_power);
+            left = Expression::Binary {
+                operator: operator.clone(),
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+
+        left
+    }
+
+    fn parse_operand(&mut self) -> Expression {
+        match self.next() {
+            Some(Item::Token(token @ Token::Minus)) => Expression::Unary {
+                operator: token.clone(),
+                operand: Box::new(self.parse_expression(PREFIX_BINDING_POWER)),
+            },
+            Some(Item::Token(token @ Token::Other(operator))) if operator == BITWISE_NOT => {
+                Expression::Unary {
+                    operator: token.clone(),
+                    operand: Box::new(self.parse_expression(PREFIX_BINDING_POWER)),
+                }
+            }
+            Some(Item::Token(Token::Other(keyword))) if keyword == defaults::NOT_KEYWORD => {
+                Expression::Unary {
+                    operator: Token::Other(keyword.clone()),
+                    operand: Box::new(self.parse_expression(NOT_BINDING_POWER)),
+                }
+            }
+            Some(Item::Token(token @ Token::Other(id_or_value))) => {
+                if KindToken::from_token(token) == KindToken::Value {
+                    return Expression::Value(token.clone());
+                }
+
+                match self.peek() {
+                    Some(Item::Arguments(arguments)) => {
+                        self.position += 1;
+
+                        Expression::Call {
+                            id: id_or_value.clone(),
+                            arguments: arguments.iter().map(Vec::as_slice).map(parse).collect(),
+                        }
+                    }
+                    _ => Expression::Variable(id_or_value.clone()),
+                }
+            }
+            Some(Item::Group(items)) => parse(items),
+            Some(Item::Block(elements)) => Expression::Block(elements.clone()),
+            item => panic!("expected an operand, found : {:?}", item),
+        }
+    }
+}
+
+#[test]
+fn operator_precedence() {
+    let token = |token: &str| Item::Token(Token::Other(token.to_string()));
+    let value = |token: &str| Box::new(Expression::Value(Token::Other(token.to_string())));
+    let variable = |id: &str| Box::new(Expression::Variable(id.to_string()));
+
+    // a + b * c - 2
+    let items = vec![
+        token("a"), Item::Token(Token::Plus), token("b"), Item::Token(Token::Multiply),
+        token("c"), Item::Token(Token::Minus), token("2"),
+    ];
+
+    assert_eq!(parse(&items), Expression::Binary {
+        operator: Token::Minus,
+        left: Box::new(Expression::Binary {
+            operator: Token::Plus,
+            left: variable("a"),
+            right: Box::new(Expression::Binary {
+                operator: Token::Multiply,
+                left: variable("b"),
+                right: variable("c"),
+            }),
+        }),
+        right: value("2"),
+    });
+
+    // -(a + 1) * 2
+    let items = vec![
+        Item::Token(Token::Minus),
+        Item::Group(vec![token("a"), Item::Token(Token::Plus), token("1")]),
+        Item::Token(Token::Multiply),
+        token("2"),
+    ];
+
+    assert_eq!(parse(&items), Expression::Binary {
+        operator: Token::Multiply,
+        left: Box::new(Expression::Unary {
+            operator: Token::Minus,
+            operand: Box::new(Expression::Binary {
+                operator: Token::Plus,
+                left: variable("a"),
+                right: value("1"),
+            }),
+        }),
+        right: value("2"),
+    });
+
+    // not a == 1 or b and c
+    let items = vec![
+        token("not"), token("a"), Item::Token(Token::Equal), token("1"),
+        token("or"), token("b"), token("and"), token("c"),
+    ];
+
+    assert_eq!(parse(&items), Expression::Binary {
+        operator: Token::Other("or".to_string()),
+        left: Box::new(Expression::Unary {
+            operator: Token::Other("not".to_string()),
+            operand: Box::new(Expression::Binary {
+                operator: Token::Equal,
+                left: variable("a"),
+                right: value("1"),
+            }),
+        }),
+        right: Box::new(Expression::Binary {
+            operator: Token::Other("and".to_string()),
+            left: variable("b"),
+            right: variable("c"),
+        }),
+    });
+
+    // a & 1 != 0
+    let items = vec![token("a"), token("&"), token("1"), token("!="), token("0")];
+
+    assert_eq!(parse(&items), Expression::Binary {
+        operator: Token::Other("!=".to_string()),
+        left: Box::new(Expression::Binary {
+            operator: Token::Other("&".to_string()),
+            left: variable("a"),
+            right: value("1"),
+        }),
+        right: value("0"),
+    });
+
+    assert_eq!(give_compound_operator(&Token::Other("<<=".to_string())), Some(Token::Other("<<".to_string())));
+    assert_eq!(give_compound_operator(&Token::Other("+=".to_string())), Some(Token::Plus));
+    assert_eq!(give_compound_operator(&Token::Other("!=".to_string())), None);
+}
@@ -0,0 +1,1740 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Lowers the parsed elements of a source file into the IR.
+//!
+//! The parser gives a flat list of elements : the body of a function is made
+//! of all the elements after it, until the next function. An element often
+//! needs the one after it, like a condition needing its expression, so the
+//! next element is kept while walking and can be skipped once used.
+
+use std::collections::HashMap as Dict;
+
+use jup::lang::{
+    elements::{
+        function::Function as ParsedFunction,
+        operation::Operation,
+        type_::Type,
+        variable::Variable,
+        Element,
+    },
+    tokens::Token,
+};
+
+use crate::{
+    defaults,
+    ir::{
+        expression::{
+            self,
+            Expression,
+            Item,
+        },
+        check::TypeError,
+        BinaryOperator,
+        Block,
+        BlockId,
+        Callee,
+        Condition,
+        Function,
+        Instruction,
+        IrType,
+        Local,
+        LocalId,
+        Module,
+        Signature,
+        Temp,
+        Terminator,
+        Value,
+    },
+    scope::Scope,
+};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum KindToken {
+    Expression,
+    Identifier,
+    Value,
+}
+
+impl KindToken {
+    pub fn from_token(id_or_value_or_expression: &Token) -> KindToken {
+        if id_or_value_or_expression == &Token::BracketOpen {
+            return KindToken::Expression;
+        }
+
+        if id_or_value_or_expression.to_string().parse::<f64>().is_ok()
+            || id_or_value_or_expression.to_string().chars().nth(0) == Some('\'') {
+            KindToken::Value
+        } else {
+            KindToken::Identifier
+        }
+    }
+}
+
+/// Condition or loop being lowered, its body is the `{ }` block after its
+/// expression
+enum ControlFlow {
+    /// Block to go when the condition is false, and end block shared by the
+    /// conditions of an "else if" chain
+    Condition { otherwise: BlockId, end: Option<BlockId>, body: BodyState },
+    /// The loop goes back to its test block, and is left to its end block
+    Loop { test: BlockId, end: BlockId, body: BodyState },
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum BodyState {
+    Expected,
+    Lowering,
+    /// Ended by the next element, except by an "else" closure after a
+    /// condition
+    Lowered,
+}
+
+impl ControlFlow {
+    fn body(&mut self) -> &mut BodyState {
+        match self {
+            ControlFlow::Condition { body, .. } => body,
+            ControlFlow::Loop { body, .. } => body,
+        }
+    }
+}
+
+/// Block of the function being lowered, its terminator is set only once
+struct BlockBuilder {
+    instructions: Vec<Instruction>,
+    terminator: Option<Terminator>,
+}
+
+/// Lowers the elements of one source file into a `Module`
+pub struct Lowerer {
+    module: Module,
+    /// Building a shared or a static library
+    is_library: bool,
+
+    /// Functions defined in the source file, with their signature
+    defined_functions: Dict<String, Signature>,
+    /// External functions declared with their signature :
+    /// `extern fun sqrt(x: double): double`
+    declared_functions: Dict<String, Signature>,
+    /// Functions defined in the other modules, from their qualified 
+    /// identifier : "math.square"
+    signatures: Dict<String, Signature>,
+    /// Functions imported from other modules with `use`, the key is the
+    /// identifier used in the source file and the value is the function's
+    /// qualified identifier
+    imports: Dict<String, String>,
+
+    function: Option<Function>,
+    blocks: Vec<BlockBuilder>,
+    current_block: BlockId,
+    /// Locals of the current function from their identifier
+    variables: Dict<String, LocalId>,
+
+    next_element: Element,
+    /// Should the next element be skipped ?
+    is_skip_next: bool,
+    /// Line of the source file of the lowered element, counted from the new
+    /// lines
+    line: usize,
+
+    /// Conditions and loops being lowered, the innermost is the last one
+    control_flow: Vec<ControlFlow>,
+    /// End block of the chain, for the condition after an "else"
+    chain_end: Option<BlockId>,
+
+    /// Value of the last lowered element, it's the value of an expression
+    last_value: Option<Value>,
+    /// Temporaries of the current function holding a floating-point literal,
+    /// with its value. A literal takes the type of the value it's used with
+    float_literals: Dict<Temp, f64>,
+    /// Local of the current function holding the value of the boolean
+    /// operations, see `lower_boolean()`
+    boolean_local: Option<LocalId>,
+
+    /// Errors preventing the module from being checked, like an unknown type
+    errors: Vec<TypeError>,
+}
+
+impl Lowerer {
+    /// The module is named from the source file's path, see
+    /// `Scope::from_path()`. The signatures of the functions from the other
+    /// modules are given by `give_signatures()`
+    pub fn new(module: Scope, is_library: bool, signatures: Dict<String, Signature>) -> Self {
+        Self {
+            module: Module {
+                id: module.to_string(),
+                functions: vec![],
+                externs: vec![],
+                signatures: vec![],
+                imports: vec![],
+                assembly: vec![],
+            },
+            is_library,
+
+            defined_functions: Dict::new(),
+            declared_functions: Dict::new(),
+            signatures,
+            imports: Dict::new(),
+
+            function: None,
+            blocks: vec![],
+            current_block: BlockId(0),
+            variables: Dict::new(),
+
+            next_element: Element::Other(Token::None),
+            is_skip_next: false,
+            line: 1,
+
+            control_flow: vec![],
+            chain_end: None,
+
+            last_value: None,
+            float_literals: Dict::new(),
+            boolean_local: None,
+
+            errors: vec![],
+        }
+    }
+
+    /// Returns the module with the errors found while lowering it, the module
+    /// is not complete when there are errors
+    pub fn lower(mut self, elements: &[Element]) -> (Module, Vec<TypeError>) {
+        self.collect_signatures(elements);
+
+        self.lower_elements(elements);
+        self.finish_function();
+
+        (self.module, self.errors)
+    }
+
+    /// Gives the signatures of the functions defined in the source file, from
+    /// their qualified identifier, to type their calls from the other modules
+    pub fn give_signatures(module: Scope, elements: &[Element]) -> Vec<(String, Signature)> {
+        let mut lowerer = Lowerer::new(module, false, Dict::new());
+        lowerer.collect_signatures(elements);
+
+        lowerer.defined_functions
+            .into_values()
+            .map(|mut signature| {
+                signature.id = format!("{}{}{}", lowerer.module.id, defaults::SCOPE_SEPARATOR, signature.id);
+                (signature.id.clone(), signature)
+            })
+            .collect()
+    }
+
+    /// Retrieves the signature of each function of the source file, before
+    /// lowering them so a function can be called before its definition
+    fn collect_signatures(&mut self, elements: &[Element]) {
+        // A function after "extern" is declared, it's defined in another
+        // object file. All the other called functions will be external 
+        // symbols too
+        for (i, element) in elements.iter().enumerate() {
+            let function = match element {
+                Element::Function(function) => function,
+                _ => continue,
+            };
+
+            let functions = match i.checked_sub(1).map(|i| &elements[i]) {
+                Some(Element::Other(Token::Other(keyword))) if keyword == defaults::EXTERN_KEYWORD => {
+                    &mut self.declared_functions
+                }
+                _ => &mut self.defined_functions,
+            };
+            functions.insert(function.id().to_string(), give_signature(function));
+        }
+    }
+
+    /// Gives the identifier of the function `id` defined in `module` as it is
+    /// written in the object files.
+    ///
+    /// The functions are mangled with their module, so two source files can
+    /// define a function with the same identifier : "square" from "math.ju" is
+    /// "math.square". The entry point is never mangled, neither are the public
+    /// functions of a library to be called from other languages
+    pub fn give_mangled_id(&self, module: &str, id: &str) -> String {
+        if id == defaults::ENTRY_POINT
+            || (self.is_library && !id.starts_with(defaults::PRIVATE_PREFIX))
+        {
+            return id.to_string();
+        }
+
+        format!("{}{}{}", module, defaults::SCOPE_SEPARATOR, id)
+    }
+
+    /// Resolves the identifier of a called function.
+    ///
+    /// The called function can be :
+    /// - defined in the current source file : `square(2)`
+    /// - qualified with its module : `math.square(2)`
+    /// - imported from its module with `use math.square` : `square(2)`
+    /// - from another language, so never mangled : `printf(format)`
+    fn resolve_function(&self, id: &str) -> Callee {
+        if self.defined_functions.contains_key(id) {
+            return Callee {
+                symbol: self.give_mangled_id(&self.module.id, id),
+                is_extern: false,
+            };
+        }
+
+        let qualified_id = match self.imports.get(id) {
+            Some(qualified_id) => qualified_id.clone(),
+            None => id.to_string(),
+        };
+
+        match qualified_id.rsplit_once(defaults::SCOPE_SEPARATOR) {
+            Some((function_module, function_id)) => Callee {
+                symbol: self.give_mangled_id(function_module, function_id),
+                is_extern: function_module != self.module.id
+                    || !self.defined_functions.contains_key(function_id),
+            },
+            None => Callee {
+                symbol: qualified_id,
+                is_extern: true,
+            },
+        }
+    }
+
+    // Walking through the elements --------------------------------------------
+
+    /// Lowers the given elements, skipping an element when `is_skip_next` is
+    /// true. The elements of an expression are lowered together
+    fn lower_elements(&mut self, elements: &[Element]) {
+        let mut i = 0;
+
+        while i < elements.len() {
+            let element = &elements[i];
+
+            if self.is_skip_next {
+                self.is_skip_next = false;
+                i += 1;
+                continue;
+            }
+
+            self.next_element = match elements.get(i + 1) {
+                Some(next_element) => next_element.clone(),
+                None => Element::Other(Token::None),
+            };
+
+            // The expression of a condition or of a loop, and an expression,
+            // can be made of several elements
+            i += match element {
+                Element::Other(Token::ConditionIf) => {
+                    self.end_control_flow(element);
+                    1 + self.lower_if(&elements[i + 1..])
+                }
+                Element::Other(Token::Loop) => {
+                    self.end_control_flow(element);
+                    1 + self.lower_loop(&elements[i + 1..])
+                }
+                element if expression::is_expression_start(element) => {
+                    self.end_control_flow(element);
+                    self.lower_expression_elements(&elements[i..])
+                }
+                element => {
+                    self.lower_element(element);
+                    1
+                }
+            };
+        }
+    }
+
+    fn lower_element(&mut self, element: &Element) {
+        self.end_control_flow(element);
+
+        match element {
+            // Arrays and parameters are lowered with the element before them
+            Element::Array(_values) => {},
+            Element::Parameters(_elements) => {},
+            Element::Assembly(code) => self.lower_assembly(code),
+            Element::Expression(elements) => self.lower_block(elements),
+            Element::Function(function) => self.lower_function(function),
+            Element::Operation(operation) => self.lower_operation(operation),
+            Element::Return(value) => self.lower_return(value),
+            Element::Variable(variable) => self.lower_variable(variable),
+            Element::Other(token) => self.lower_other(token),
+        }
+    }
+
+    /// Ends the conditions and loops whose body is lowered, when the element
+    /// is not part of them
+    fn end_control_flow(&mut self, element: &Element) {
+        while let Some(control_flow) = self.control_flow.last_mut() {
+            if *control_flow.body() != BodyState::Lowered {
+                break;
+            }
+
+            match (control_flow, element) {
+                (_, Element::Other(Token::NewLine)) => break,
+                (ControlFlow::Condition { .. }, Element::Other(Token::ConditionElse)) => break,
+                _ => self.close_control_flow(),
+            }
+        }
+    }
+
+    /// Lowers a `{ }` block, as the body of the innermost condition or loop
+    /// when it's expected
+    fn lower_block(&mut self, elements: &[Element]) {
+        let is_body = match self.control_flow.last_mut().map(|control_flow| control_flow.body()) {
+            Some(body) if *body == BodyState::Expected => {
+                *body = BodyState::Lowering;
+                true
+            }
+            _ => false,
+        };
+
+        self.lower_block_scope(elements);
+
+        if is_body {
+            *self.control_flow.last_mut().unwrap().body() = BodyState::Lowered;
+        }
+    }
+
+    /// The variables declared in a `{ }` block are only visible in it. They
+    /// can hide the ones with the same identifier declared before, which are
+    /// visible again after the block
+    fn lower_block_scope(&mut self, elements: &[Element]) {
+        let variables = self.variables.clone();
+        let next_element = self.next_element.clone();
+        let depth = self.control_flow.len();
+
+        self.lower_elements(elements);
+
+        // The conditions and loops of the block end with it
+        while self.control_flow.len() > depth {
+            self.close_control_flow();
+        }
+
+        self.variables = variables;
+        self.next_element = next_element;
+        self.is_skip_next = false;
+    }
+
+    /// Lowers the expression starting at the first element and gives the
+    /// number of elements it's made of
+    fn lower_expression_elements(&mut self, elements: &[Element]) -> usize {
+        let (items, n_elements) = expression::flatten(elements);
+
+        // Compound assignment : `a += 1`
+        if let [Item::Token(Token::Other(id)), Item::Token(assignment), value @ ..] = items.as_slice() {
+            if let Some(operator) = expression::give_compound_operator(assignment) {
+                self.lower_compound_assignment(id, operator, value);
+                return n_elements;
+            }
+        }
+
+        let expression = expression::parse(&items);
+
+        self.last_value = Some(self.lower_expression(&expression));
+        n_elements
+    }
+
+    fn lower_compound_assignment(&mut self, id: &str, operator: Token, value: &[Item]) {
+        let expression = Expression::Binary {
+            operator,
+            left: Box::new(Expression::Variable(id.to_string())),
+            right: Box::new(expression::parse(value)),
+        };
+
+        let value = self.lower_expression(&expression);
+        let local = self.give_local(id);
+
+        self.emit_store(local, 0, value);
+    }
+
+    /// Lowers the expression starting at the element, it can go on with the
+    /// next element, which is then skipped
+    fn lower_expression_at(&mut self, element: Element) {
+        let elements = [element, self.next_element.clone()];
+
+        if self.lower_expression_elements(&elements) > 1 {
+            self.is_skip_next = true;
+        }
+    }
+
+    /// Lowers the expression written in the first elements as a condition,
+    /// going to `then` when it's true and to `otherwise` else. Returns the
+    /// number of elements of the expression
+    fn lower_branch_elements(&mut self, elements: &[Element], then: BlockId, otherwise: BlockId) -> usize {
+        let (items, n_elements) = expression::flatten(elements);
+        let expression = expression::parse(&items);
+
+        self.lower_branch(&expression, then, otherwise);
+        n_elements
+    }
+
+    /// The "and", "or" and "not" operators of a condition are jumps : the
+    /// right operand is only computed when the left one does not give the
+    /// result
+    fn lower_branch(&mut self, expression: &Expression, then: BlockId, otherwise: BlockId) {
+        match expression {
+            Expression::Binary { operator: Token::Other(keyword), left, right }
+                if keyword == defaults::AND_KEYWORD || keyword == defaults::OR_KEYWORD =>
+            {
+                let right_block = self.new_block();
+
+                if keyword == defaults::AND_KEYWORD {
+                    self.lower_branch(left, right_block, otherwise);
+                } else {
+                    self.lower_branch(left, then, right_block);
+                }
+
+                self.current_block = right_block;
+                self.lower_branch(right, then, otherwise);
+            }
+            Expression::Unary { operator: Token::Other(keyword), operand }
+                if keyword == defaults::NOT_KEYWORD =>
+            {
+                self.lower_branch(operand, otherwise, then);
+            }
+            expression => {
+                let condition = self.lower_expression(expression);
+                self.terminate(Terminator::Branch { condition, then, otherwise });
+            }
+        }
+    }
+
+    /// The value of a boolean operation is one when it's true, zero else. It's
+    /// written from the blocks of the condition in a local, since a temporary
+    /// is assigned once. The local is shared by the function's boolean 
+    /// operations, each value is loaded before the next one is written
+    fn lower_boolean(&mut self, expression: &Expression) -> Value {
+        let local = match self.boolean_local {
+            Some(local) => local,
+            None => {
+                let function = self.function();
+                function.locals.push(Local { id: "boolean".to_string(), type_: IrType::Byte });
+
+                let local = LocalId(function.locals.len() - 1);
+                self.boolean_local = Some(local);
+                local
+            }
+        };
+
+        let is_true = self.new_block();
+        let is_false = self.new_block();
+        let end = self.new_block();
+
+        self.lower_branch(expression, is_true, is_false);
+
+        for (block, value) in [(is_true, 1), (is_false, 0)] {
+            self.current_block = block;
+            self.emit(Instruction::Store { local, index: 0, value: Value::Constant(value) });
+            self.terminate(Terminator::Jump(end));
+        }
+
+        self.current_block = end;
+        self.load_variable_local(local)
+    }
+
+    /// Lowers the operands before their operation, each result is a new
+    /// temporary
+    fn lower_expression(&mut self, expression: &Expression) -> Value {
+        match expression {
+            Expression::Value(value) => self.lower_constant(value),
+            Expression::Variable(id) => self.load_variable(id),
+            Expression::Block(elements) => {
+                self.last_value = None;
+                self.lower_block_scope(elements);
+                self.last_value.unwrap_or(Value::Constant(0))
+            }
+            Expression::Call { id, arguments } => self.lower_call(id, arguments),
+            Expression::Unary { operator: Token::Other(keyword), .. }
+            | Expression::Binary { operator: Token::Other(keyword), .. }
+                if is_boolean_keyword(keyword) =>
+            {
+                self.lower_boolean(expression)
+            }
+            Expression::Unary { operator, operand } => {
+                let operand = self.lower_expression(operand);
+
+                match operator {
+                    // -x is 0 - x
+                    Token::Minus => self.emit_binary(BinaryOperator::Subtract, Value::Constant(0), operand),
+                    // ~x is x ^ -1
+                    Token::Other(operator) if operator == expression::BITWISE_NOT => {
+                        self.emit_binary(BinaryOperator::BitwiseXor, operand, Value::Constant(-1))
+                    }
+                    _ => panic!("invalid unary operator : {:?}", operator),
+                }
+            }
+            Expression::Binary { operator, left, right } => {
+                let left = self.lower_expression(left);
+                let right = self.lower_expression(right);
+
+                match (give_binary_operator(operator), give_condition(operator)) {
+                    (Some(operator), _) => self.emit_binary(operator, left, right),
+                    (None, Some(condition)) => self.emit_comparison(condition, left, right),
+                    (None, None) => panic!("invalid binary operator : {:?}", operator),
+                }
+            }
+        }
+    }
+
+    /// Lowers the next element as an expression and gives its value, then
+    /// skips it
+    fn lower_next_expression(&mut self) -> Value {
+        let expression = self.next_element.clone();
+
+        // The element after the expression is not known
+        self.next_element = Element::Other(Token::None);
+
+        self.last_value = None;
+        self.lower_element(&expression);
+        self.is_skip_next = true;
+
+        self.last_value.unwrap_or(Value::Constant(0))
+    }
+
+    // Functions for the elements ----------------------------------------------
+
+    fn lower_assembly(&mut self, code: &Token) {
+        if self.function.is_some() {
+            self.emit(Instruction::Assembly(code.to_string()));
+        } else {
+            self.module.assembly.push(code.to_string());
+        }
+    }
+
+    /// Terminates the previous function, then starts the new one with its
+    /// parameters as locals
+    fn lower_function(&mut self, parsed: &ParsedFunction) {
+        self.finish_function();
+        self.float_literals.clear();
+        self.boolean_local = None;
+
+        let signature = self.lower_signature(parsed);
+
+        self.function = Some(Function {
+            symbol: self.give_mangled_id(&self.module.id, &signature.id),
+            id: signature.id,
+            parameters: vec![],
+            return_type: signature.return_type,
+            locals: vec![],
+            temps: vec![],
+            blocks: vec![],
+        });
+        self.current_block = self.new_block();
+
+        for parameter in signature.parameters {
+            let local = self.new_local(parameter.id, parameter.type_);
+            self.function().parameters.push(local);
+        }
+    }
+
+    /// Gives the signature of the parsed function, its unknown types are
+    /// reported
+    fn lower_signature(&mut self, parsed: &ParsedFunction) -> Signature {
+        let id = parsed.id().to_string();
+
+        let return_type = self.lower_type(parsed.return_type(), &id);
+        let parameters = give_parameters(parsed)
+            .into_iter()
+            .map(|(parameter_id, type_)| Local {
+                id: parameter_id,
+                type_: self.lower_type(&type_, &id),
+            })
+            .collect();
+
+        Signature { id, parameters, return_type }
+    }
+
+    fn lower_operation(&mut self, operation: &Operation) {
+        match operation.operator() {
+            Token::Assign => self.lower_assign(operation),
+            _ => self.lower_expression_at(Element::Operation(operation.clone())),
+        }
+    }
+
+    /// ```text
+    /// <arg1> <operator> <arg2>
+    /// variable_to_assign = value
+    /// ```
+    fn lower_assign(&mut self, operation: &Operation) {
+        let local = self.give_local(&operation.arg1().to_string());
+
+        match operation.arg2() {
+            Token::SquareBracketOpen => self.lower_array_assignment(local),
+            value => {
+                let value = self.lower_token(value);
+                self.emit_store(local, 0, value);
+            }
+        }
+    }
+
+    fn lower_return(&mut self, value: &Token) {
+        let value = match value {
+            Token::None => None,
+            value => {
+                let value = self.lower_token(value);
+                let return_type = self.function().return_type.clone();
+
+                if return_type.is_float() {
+                    Some(self.convert_float(value, &return_type))
+                } else {
+                    Some(value)
+                }
+            }
+        };
+
+        self.terminate(Terminator::Return(value));
+    }
+
+    /// Creates a local for the variable and assigns it
+    fn lower_variable(&mut self, variable: &Variable) {
+        let function_id = self.function().id.clone();
+        let mut type_ = self.lower_type(variable.type_(), &function_id);
+
+        match variable.value() {
+            Token::None => {
+                self.new_local(variable.id(), type_);
+            }
+            Token::SquareBracketOpen => {
+                // A static array has the length of its assigned array
+                if let (IrType::Array(element_type, 0), Element::Array(values))
+                    = (&type_, &self.next_element)
+                {
+                    type_ = IrType::Array(element_type.clone(), values.len());
+                }
+
+                let local = self.new_local(variable.id(), type_);
+                self.lower_array_assignment(local);
+            }
+            value => {
+                let value = self.lower_token(value);
+                let local = self.new_local(variable.id(), type_);
+
+                self.emit_store(local, 0, value);
+            }
+        }
+    }
+
+    fn lower_other(&mut self, other: &Token) {
+        match other {
+            Token::ConditionElse => self.lower_else(),
+            Token::LoopBreak | Token::LoopContinue => self.lower_loop_exit(other),
+            Token::NewLine => self.line += 1,
+            Token::Other(keyword) if keyword == defaults::USE_KEYWORD => {
+                self.lower_use();
+            }
+            Token::Other(keyword) if keyword == defaults::EXTERN_KEYWORD => {
+                self.lower_extern();
+            }
+            Token::Other(_id_or_value) => self.lower_expression_at(Element::Other(other.clone())),
+            _ => panic!("unknown token : {:?}", other),
+        }
+    }
+
+    /// Lowers the condition written in the first elements, then its body is
+    /// expected. Without "else" closure, the condition is ended by
+    /// `end_control_flow()`.
+    ///
+    /// Returns the number of elements of the condition's expression
+    fn lower_if(&mut self, elements: &[Element]) -> usize {
+        let then = self.new_block();
+        let otherwise = self.new_block();
+
+        let n_elements = self.lower_branch_elements(elements, then, otherwise);
+
+        self.current_block = then;
+        self.control_flow.push(ControlFlow::Condition {
+            otherwise,
+            end: self.chain_end.take(),
+            body: BodyState::Expected,
+        });
+
+        n_elements
+    }
+
+    /// Each body of an "else if" chain goes to the same end block, the
+    /// conditions are tested in order
+    fn lower_else(&mut self) {
+        let (otherwise, end) = match self.control_flow.pop() {
+            Some(ControlFlow::Condition { otherwise, end, body: BodyState::Lowered }) => {
+                (otherwise, end)
+            }
+            _ => panic!("\"else\" closure without condition"),
+        };
+
+        let end = end.unwrap_or_else(|| self.new_block());
+        self.terminate(Terminator::Jump(end));
+        self.current_block = otherwise;
+
+        if self.next_element == Element::Other(Token::ConditionIf) {
+            self.chain_end = Some(end);
+            return;
+        }
+
+        self.lower_next_expression();
+        self.switch_to(end);
+    }
+
+    /// The loop is done while its condition, written in the first elements, is
+    /// true. It's ended by `end_control_flow()`.
+    ///
+    /// Returns the number of elements of the loop's expression
+    fn lower_loop(&mut self, elements: &[Element]) -> usize {
+        let test = self.new_block();
+        self.switch_to(test);
+
+        let body = self.new_block();
+        let end = self.new_block();
+
+        let n_elements = self.lower_branch_elements(elements, body, end);
+
+        self.current_block = body;
+        self.control_flow.push(ControlFlow::Loop {
+            test,
+            end,
+            body: BodyState::Expected,
+        });
+
+        n_elements
+    }
+
+    /// A "break" or a "continue" is for the innermost loop
+    fn lower_loop_exit(&mut self, token: &Token) {
+        let (test, end) = self.control_flow
+            .iter()
+            .rev()
+            .find_map(|control_flow| match control_flow {
+                ControlFlow::Loop { test, end, .. } => Some((*test, *end)),
+                ControlFlow::Condition { .. } => None,
+            })
+            .unwrap_or_else(|| panic!("\"break\" or \"continue\" outside of a loop"));
+
+        let target = if token == &Token::LoopBreak { end } else { test };
+        self.terminate(Terminator::Jump(target));
+
+        // The rest of the body is never reached
+        self.current_block = self.new_block();
+    }
+
+    /// Ends the innermost condition or loop
+    fn close_control_flow(&mut self) {
+        match self.control_flow.pop() {
+            Some(ControlFlow::Condition { otherwise, end, .. }) => {
+                self.switch_to(otherwise);
+
+                if let Some(end) = end {
+                    self.switch_to(end);
+                }
+            }
+            Some(ControlFlow::Loop { test, end, .. }) => {
+                self.terminate(Terminator::Jump(test));
+                self.current_block = end;
+            }
+            None => {}
+        }
+    }
+
+    /// Imports a function from another module to call it without writing its
+    /// module : `use math.square` then `square(2)`.
+    ///
+    /// Importing a whole module (`use math`) imports nothing, its functions are
+    /// called with their qualified identifier : `math.square(2)`
+    fn lower_use(&mut self) {
+        let qualified_id = match self.next_element.clone() {
+            Element::Other(Token::Other(qualified_id)) => qualified_id,
+            element => panic!("invalid import : {:?}", element),
+        };
+        self.is_skip_next = true;
+
+        if let Some((_module, id)) = qualified_id.rsplit_once(defaults::SCOPE_SEPARATOR) {
+            self.imports.insert(id.to_string(), qualified_id.clone());
+            self.module.imports.push((id.to_string(), qualified_id.clone()));
+        }
+    }
+
+    /// Declares the function written after "extern", it's defined in another
+    /// object file : `extern fun sqrt(x: double): double`. Its signature is
+    /// known from `lower()`, the code after it is not part of a function
+    fn lower_extern(&mut self) {
+        let parsed = match self.next_element.clone() {
+            Element::Function(parsed) => parsed,
+            element => panic!("invalid external function : {:?}", element),
+        };
+        self.is_skip_next = true;
+
+        self.finish_function();
+        // Reports its unknown types
+        self.lower_signature(&parsed);
+    }
+
+    /// A function which is not defined in the current file is declared as an
+    /// external symbol, it can come from another source file or from a linked
+    /// library like the C library.
+    ///
+    /// A function with a known signature, see `find_signature()`, has its 
+    /// floating-point parameters receiving their argument converted, and its
+    /// result has the return type. The result of another function is a big
+    /// integer, the size of the return register
+    fn lower_call(&mut self, id: &str, arguments: &[Expression]) -> Value {
+        if !self.defined_functions.contains_key(id) {
+            if let Some(type_) = IrType::from_cast_id(id) {
+                return self.lower_cast(type_, arguments);
+            }
+        }
+
+        let callee = self.resolve_function(id);
+        let signature = self.find_signature(id).cloned();
+
+        if callee.is_extern && !self.module.externs.contains(&callee.symbol) {
+            self.module.externs.push(callee.symbol.clone());
+
+            if let Some(signature) = &signature {
+                self.module.signatures.push((callee.symbol.clone(), signature.clone()));
+            }
+        }
+
+        // The arguments are computed from left to right, each one in its own
+        // temporary
+        let arguments: Vec<Value> = arguments
+            .iter()
+            .enumerate()
+            .map(|(i, argument)| {
+                let value = self.lower_expression(argument);
+
+                match signature.as_ref().and_then(|signature| signature.parameters.get(i)) {
+                    Some(parameter) if parameter.type_.is_float() => {
+                        self.convert_float(value, &parameter.type_)
+                    }
+                    _ => value,
+                }
+            })
+            .collect();
+
+        let return_type = match signature {
+            Some(signature) => signature.return_type,
+            None => IrType::BigInteger,
+        };
+
+        let dest = self.new_temp(return_type);
+        self.emit(Instruction::Call { dest, callee, arguments });
+
+        Value::Temp(dest)
+    }
+
+    /// Gives the signature of a called function defined or declared in the
+    /// current file, or defined in another module
+    fn find_signature(&self, id: &str) -> Option<&Signature> {
+        if let Some(signature) = self.defined_functions.get(id) {
+            return Some(signature);
+        }
+        if let Some(signature) = self.declared_functions.get(id) {
+            return Some(signature);
+        }
+
+        let qualified_id = match self.imports.get(id) {
+            Some(qualified_id) => qualified_id.as_str(),
+            None => id,
+        };
+
+        match qualified_id.rsplit_once(defaults::SCOPE_SEPARATOR) {
+            Some((module, function_id)) if module == self.module.id => {
+                self.defined_functions.get(function_id)
+            }
+            Some(_module_and_id) => self.signatures.get(qualified_id),
+            None => None,
+        }
+    }
+
+    /// Converts the only passed parameter to the type, written like a call :
+    /// `byte(a)`
+    fn lower_cast(&mut self, type_: IrType, arguments: &[Expression]) -> Value {
+        let value = match arguments {
+            [argument] => self.lower_expression(argument),
+            _ => panic!("a cast takes one parameter : {:?}", arguments),
+        };
+
+        let dest = self.new_temp(type_);
+        self.emit(Instruction::Cast { dest, value });
+
+        Value::Temp(dest)
+    }
+
+    /// Stores each value of the next element, an array, in the array local
+    fn lower_array_assignment(&mut self, local: LocalId) {
+        let values: Vec<Token> = match self.next_element.clone() {
+            Element::Array(values) => values,
+            _ => panic!("try to assign a non-array element to an array variable"),
+        };
+
+        for (index, value) in values.iter().enumerate() {
+            let value = self.lower_token(value);
+            self.emit_store(local, index, value);
+        }
+        self.is_skip_next = true;
+    }
+
+    // Other functions for the IR ----------------------------------------------
+
+    /// Gives the value of an identifier, a value or an expression (then it's
+    /// the next element). An identifier followed by passed parameters is a 
+    /// call : `let a: int = foo(1)`
+    fn lower_token(&mut self, id_or_value_or_expression: &Token) -> Value {
+        match KindToken::from_token(id_or_value_or_expression) {
+            KindToken::Expression => self.lower_next_expression(),
+            KindToken::Identifier if matches!(self.next_element, Element::Parameters(_)) => {
+                let elements = [
+                    Element::Other(id_or_value_or_expression.clone()), 
+                    self.next_element.clone(),
+                ];
+                self.is_skip_next = true;
+
+                let (items, _n_elements) = expression::flatten(&elements);
+                self.lower_expression(&expression::parse(&items))
+            }
+            KindToken::Identifier => self.load_variable(&id_or_value_or_expression.to_string()),
+            KindToken::Value => self.lower_constant(id_or_value_or_expression),
+        }
+    }
+
+    /// Converts a type from the parser, for the function `function`. An 
+    /// unknown type is reported and replaced by a big integer, so the lowering
+    /// goes on to report the next errors
+    fn lower_type(&mut self, type_: &Type, function: &str) -> IrType {
+        if let Some(ir_type) = IrType::from_type(type_) {
+            return ir_type;
+        }
+
+        let type_ = match type_ {
+            Type::Other(id) => id.clone(),
+            type_ => format!("{:?}", type_),
+        };
+        self.errors.push(TypeError::UnknownType {
+            function: function.to_string(),
+            line: self.line,
+            type_,
+        });
+
+        IrType::BigInteger
+    }
+
+    fn load_variable(&mut self, id: &str) -> Value {
+        let local = self.give_local(id);
+        self.load_variable_local(local)
+    }
+
+    fn load_variable_local(&mut self, local: LocalId) -> Value {
+        let type_ = match &self.function().locals[local.0].type_ {
+            IrType::Array(element_type, _length) => *element_type.clone(),
+            type_ => type_.clone(),
+        };
+
+        let dest = self.new_temp(type_);
+        self.emit(Instruction::Load { dest, local, index: 0 });
+
+        Value::Temp(dest)
+    }
+
+    fn give_local(&mut self, id: &str) -> LocalId {
+        match self.variables.get(id) {
+            Some(local) => *local,
+            None => panic!("unknown variable : {}", id),
+        }
+    }
+
+    /// The type of a result is the widest type of its temporary operands, so
+    /// it's never narrowed without a cast. The constants are integers
+    fn give_type(&mut self, values: &[Value]) -> IrType {
+        let mut type_: Option<IrType> = None;
+
+        for value in values {
+            if let Value::Temp(temp) = value {
+                let temp_type = self.function().temps[temp.0].clone();
+
+                match &type_ {
+                    Some(widest) if widest.size() >= temp_type.size() => {},
+                    _ => type_ = Some(temp_type),
+                }
+            }
+        }
+
+        type_.unwrap_or(IrType::Integer)
+    }
+
+    /// The division, the modulo and the right shift of unsigned values are
+    /// not the signed ones. A floating-point value can only be added, 
+    /// subtracted, multiplied and divided
+    fn emit_binary(&mut self, operator: BinaryOperator, left: Value, right: Value) -> Value {
+        if let Some(type_) = self.give_float_type(&[left, right]) {
+            if !matches!(
+                operator,
+                BinaryOperator::Add
+                    | BinaryOperator::Subtract
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Divide
+            ) {
+                panic!("unsupported operation on floating-point values : {}", operator);
+            }
+
+            let left = self.convert_float(left, &type_);
+            let right = self.convert_float(right, &type_);
+            let dest = self.new_temp(type_);
+
+            self.emit(Instruction::Binary { dest, operator, left, right });
+            return Value::Temp(dest);
+        }
+
+        // The count of a shift does not change the type of the shifted value
+        let type_ = match (operator, left) {
+            (
+                BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight,
+                Value::Temp(_temp)
+            ) => self.give_type(&[left]),
+            _ => self.give_type(&[left, right]),
+        };
+        let operator = if type_.is_unsigned() { operator.to_unsigned() } else { operator };
+        let dest = self.new_temp(type_);
+
+        self.emit(Instruction::Binary { dest, operator, left, right });
+        Value::Temp(dest)
+    }
+
+    fn emit_comparison(&mut self, condition: Condition, left: Value, right: Value) -> Value {
+        let (left, right) = match self.give_float_type(&[left, right]) {
+            Some(type_) => (self.convert_float(left, &type_), self.convert_float(right, &type_)),
+            None => (left, right),
+        };
+
+        let condition = if self.give_type(&[left, right]).is_unsigned() {
+            condition.to_unsigned()
+        } else {
+            condition
+        };
+        let dest = self.new_temp(IrType::Byte);
+
+        self.emit(Instruction::Compare { dest, condition, left, right });
+        Value::Temp(dest)
+    }
+
+    /// A value given to a floating-point local is converted to its type
+    fn emit_store(&mut self, local: LocalId, index: usize, value: Value) {
+        let type_ = self.function().locals[local.0].type_.element_type().clone();
+
+        let value = if type_.is_float() {
+            self.convert_float(value, &type_)
+        } else {
+            value
+        };
+
+        self.emit(Instruction::Store { local, index, value });
+    }
+
+    /// Gives the value of a number or of a character. A floating-point literal
+    /// is a double until it's used with a float
+    fn lower_constant(&mut self, value: &Token) -> Value {
+        if let Some(literal) = give_float_literal(value) {
+            return self.emit_float_literal(literal, IrType::Double);
+        }
+
+        // Only an unsigned big integer can hold it, its constant is the bits
+        // of its value
+        if let Some(constant) = give_big_unsigned_literal(value) {
+            let dest = self.new_temp(IrType::UnsignedBigInteger);
+            self.emit(Instruction::Copy { dest, value: Value::Constant(constant as i64) });
+
+            return Value::Temp(dest);
+        }
+
+        give_constant(value)
+    }
+
+    /// Copies the bits of the literal written in the type to a temporary
+    fn emit_float_literal(&mut self, literal: f64, type_: IrType) -> Value {
+        let constant = match type_ {
+            IrType::Float => (literal as f32).to_bits() as i64,
+            _ => literal.to_bits() as i64,
+        };
+
+        let dest = self.new_temp(type_);
+        self.emit(Instruction::Copy { dest, value: Value::Constant(constant) });
+        self.float_literals.insert(dest, literal);
+
+        Value::Temp(dest)
+    }
+
+    /// Gives the floating-point type of an operation on the values, if one of
+    /// them is a floating-point value : a double when one of them is a double
+    /// which is not a literal, else a float. The literals alone are doubles
+    fn give_float_type(&mut self, values: &[Value]) -> Option<IrType> {
+        let mut types: Vec<IrType> = vec![];
+        let mut has_literal = false;
+
+        for value in values {
+            if let Value::Temp(temp) = value {
+                let type_ = self.function().temps[temp.0].clone();
+
+                if self.float_literals.contains_key(temp) {
+                    has_literal = true;
+                } else if type_.is_float() {
+                    types.push(type_);
+                }
+            }
+        }
+
+        if types.contains(&IrType::Double) || (types.is_empty() && has_literal) {
+            Some(IrType::Double)
+        } else if types.is_empty() {
+            None
+        } else {
+            Some(IrType::Float)
+        }
+    }
+
+    /// Converts the value to the floating-point type : a constant or a literal
+    /// is written again in this type, and a float is widened to a double. The
+    /// other values are kept, they are reported by the type checking
+    fn convert_float(&mut self, value: Value, type_: &IrType) -> Value {
+        let temp = match value {
+            Value::Constant(constant) => return self.emit_float_literal(constant as f64, type_.clone()),
+            Value::Temp(temp) => temp,
+        };
+
+        let temp_type = self.function().temps[temp.0].clone();
+
+        if &temp_type == type_ {
+            return value;
+        }
+
+        if let Some(literal) = self.float_literals.get(&temp).copied() {
+            return self.emit_float_literal(literal, type_.clone());
+        }
+
+        if temp_type == IrType::Float && type_ == &IrType::Double {
+            let dest = self.new_temp(IrType::Double);
+            self.emit(Instruction::Cast { dest, value });
+            return Value::Temp(dest);
+        }
+
+        value
+    }
+
+    fn function(&mut self) -> &mut Function {
+        match self.function.as_mut() {
+            Some(function) => function,
+            None => panic!("code outside of a function"),
+        }
+    }
+
+    fn new_temp(&mut self, type_: IrType) -> Temp {
+        let temps = &mut self.function().temps;
+        temps.push(type_);
+
+        Temp(temps.len() - 1)
+    }
+
+    /// A new local with the same identifier as another one hides it
+    fn new_local(&mut self, id: String, type_: IrType) -> LocalId {
+        let locals = &mut self.function().locals;
+        locals.push(Local { id: id.clone(), type_ });
+
+        let local = LocalId(locals.len() - 1);
+        self.variables.insert(id, local);
+
+        local
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BlockBuilder {
+            instructions: vec![],
+            terminator: None,
+        });
+
+        BlockId(self.blocks.len() - 1)
+    }
+
+    /// Adds the instruction to the current block. After a terminator, the
+    /// instruction is not reachable but it's still added in a new block
+    fn emit(&mut self, instruction: Instruction) {
+        // Panics when outside of a function
+        self.function();
+
+        if self.blocks[self.current_block.0].terminator.is_some() {
+            self.current_block = self.new_block();
+        }
+
+        self.emit_line();
+        self.blocks[self.current_block.0].instructions.push(instruction);
+    }
+
+    /// Marks the line of the next instructions in the current block, when it
+    /// changed since the last instruction of the block
+    fn emit_line(&mut self) {
+        let instructions = &mut self.blocks[self.current_block.0].instructions;
+
+        let last_line = instructions.iter().rev().find_map(|instruction| match instruction {
+            Instruction::Line(line) => Some(*line),
+            _ => None,
+        });
+
+        if last_line != Some(self.line) {
+            instructions.push(Instruction::Line(self.line));
+        }
+    }
+
+    /// Terminates the current block, nothing is done when it's already
+    /// terminated
+    fn terminate(&mut self, terminator: Terminator) {
+        // Panics when outside of a function
+        self.function();
+
+        if self.blocks[self.current_block.0].terminator.is_some() {
+            return;
+        }
+
+        // The returned value is checked with the line of the return
+        if matches!(terminator, Terminator::Return(_)) {
+            self.emit_line();
+        }
+        self.blocks[self.current_block.0].terminator = Some(terminator);
+    }
+
+    /// Continues in another block, the current one goes to it when it's not
+    /// terminated
+    fn switch_to(&mut self, block: BlockId) {
+        self.terminate(Terminator::Jump(block));
+        self.current_block = block;
+    }
+
+    /// Adds the function to the module, the blocks without terminator return
+    fn finish_function(&mut self) {
+        if self.function.is_none() {
+            return;
+        }
+
+        while !self.control_flow.is_empty() {
+            self.close_control_flow();
+        }
+
+        let mut function = self.function.take().unwrap();
+
+        function.blocks = self.blocks.drain(..)
+            .enumerate()
+            .map(|(i, block)| Block {
+                id: BlockId(i),
+                instructions: block.instructions,
+                terminator: block.terminator.unwrap_or(Terminator::Return(None)),
+            })
+            .collect();
+
+        self.module.functions.push(function);
+        self.variables = Dict::new();
+    }
+}
+
+/// Gives the identifier and the type of each parameter of the function, a
+/// parameter is written as its identifier then its type
+fn give_parameters(parsed: &ParsedFunction) -> Vec<(String, Type)> {
+    // Retrieves the function's parameters because it's an `Element`.
+    //
+    // The panic! will never happen.
+    let elements = match parsed.params() {
+        Element::Parameters(elements) => elements,
+        _ => panic!("parameters are not a parameters element"),
+    };
+
+    let mut parameters: Vec<(String, Type)> = vec![];
+    let mut parameter_id: Option<String> = None;
+
+    for element in elements {
+        let token = match element {
+            Element::Other(token) => token,
+            _ => panic!("passed parameter is not valid : {:?}", element)
+        };
+
+        let id_or_type = match token {
+            Token::TypeDef | Token::Comma => continue,
+            Token::Other(ref id_or_type) => id_or_type,
+            _ => panic!("invalid token found in parameter list : {:?}", token)
+        };
+
+        match parameter_id.take() {
+            None => parameter_id = Some(id_or_type.clone()),
+            Some(id) => parameters.push((id, Type::from_string(id_or_type.clone()))),
+        }
+    }
+
+    parameters
+}
+
+/// Gives the signature of the parsed function to type its calls, an unknown
+/// type is a big integer. It's reported once the function is lowered
+fn give_signature(parsed: &ParsedFunction) -> Signature {
+    let lower_type = |type_: &Type| IrType::from_type(type_).unwrap_or(IrType::BigInteger);
+
+    Signature {
+        id: parsed.id().to_string(),
+        parameters: give_parameters(parsed)
+            .into_iter()
+            .map(|(id, type_)| Local { id, type_: lower_type(&type_) })
+            .collect(),
+        return_type: lower_type(parsed.return_type()),
+    }
+}
+
+fn is_boolean_keyword(keyword: &str) -> bool {
+    keyword == defaults::AND_KEYWORD
+        || keyword == defaults::OR_KEYWORD
+        || keyword == defaults::NOT_KEYWORD
+}
+
+fn give_binary_operator(operator: &Token) -> Option<BinaryOperator> {
+    match operator {
+        Token::Plus => Some(BinaryOperator::Add),
+        Token::Minus => Some(BinaryOperator::Subtract),
+        Token::Multiply => Some(BinaryOperator::Multiply),
+        Token::Divide => Some(BinaryOperator::Divide),
+        Token::Other(operator) => match operator.as_str() {
+            expression::MODULO => Some(BinaryOperator::Modulo),
+            expression::BITWISE_AND => Some(BinaryOperator::BitwiseAnd),
+            expression::BITWISE_OR => Some(BinaryOperator::BitwiseOr),
+            expression::BITWISE_XOR => Some(BinaryOperator::BitwiseXor),
+            expression::SHIFT_LEFT => Some(BinaryOperator::ShiftLeft),
+            expression::SHIFT_RIGHT => Some(BinaryOperator::ShiftRight),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn give_condition(operator: &Token) -> Option<Condition> {
+    match operator {
+        Token::Equal => Some(Condition::Equal),
+        Token::LessThan => Some(Condition::LessThan),
+        Token::MoreThan => Some(Condition::MoreThan),
+        Token::LessThanOrEqual => Some(Condition::LessThanOrEqual),
+        Token::MoreThanOrEqual => Some(Condition::MoreThanOrEqual),
+        Token::Other(operator) if operator == expression::NOT_EQUAL => Some(Condition::NotEqual),
+        _ => None,
+    }
+}
+
+/// Gives the value of a number written with a decimal part or an exponent
+fn give_float_literal(value: &Token) -> Option<f64> {
+    let value = value.to_string();
+
+    match (value.parse::<i64>(), value.parse::<u64>()) {
+        (Err(_), Err(_)) => value.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Gives the value of a number above the biggest big integer
+fn give_big_unsigned_literal(value: &Token) -> Option<u64> {
+    let value = value.to_string();
+
+    match value.parse::<i64>() {
+        Ok(_constant) => None,
+        Err(_) => value.parse::<u64>().ok(),
+    }
+}
+
+/// Gives the value of a number or of a character like 'a'
+fn give_constant(value: &Token) -> Value {
+    let value = value.to_string();
+
+    if let Ok(constant) = value.parse::<i64>() {
+        return Value::Constant(constant);
+    }
+
+    let character = value.strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+        .unwrap_or_else(|| panic!("unsupported value : {}", value));
+
+    let mut characters = character.chars();
+
+    let character = match (characters.next(), characters.next(), characters.next()) {
+        (Some('\\'), Some(escaped), None) => match escaped {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            escaped => escaped,
+        },
+        (Some(character), None, None) => character,
+        _ => panic!("unsupported value : {}", value),
+    };
+
+    Value::Constant(character as i64)
+}
+
+/// Starts lowering the "main" function of "main.ju", with the given
+/// parameters
+#[cfg(test)]
+fn give_test_lowerer(signatures: Dict<String, Signature>, parameters: Vec<(&str, IrType)>) -> Lowerer {
+    let mut lowerer = Lowerer::new(Scope::from_path("main.ju"), false, signatures);
+
+    lowerer.function = Some(Function {
+        id: "main".to_string(),
+        symbol: "main".to_string(),
+        parameters: vec![],
+        return_type: IrType::Void,
+        locals: vec![],
+        temps: vec![],
+        blocks: vec![],
+    });
+    lowerer.current_block = lowerer.new_block();
+
+    for (id, type_) in parameters {
+        let local = lowerer.new_local(id.to_string(), type_);
+        lowerer.function().parameters.push(local);
+    }
+
+    lowerer
+}
+
+#[test]
+fn extern_calls() {
+    // extern fun sqrt(x: double): double
+    //
+    // fun main {
+    //     sqrt(2)
+    // }
+    let mut lowerer = give_test_lowerer(Dict::new(), vec![]);
+    let signature = Signature {
+        id: "sqrt".to_string(),
+        parameters: vec![Local { id: "x".to_string(), type_: IrType::Double }],
+        return_type: IrType::Double,
+    };
+    lowerer.declared_functions.insert("sqrt".to_string(), signature.clone());
+
+    lowerer.lower_elements(&[
+        Element::Other(Token::Other("sqrt".to_string())),
+        Element::Parameters(vec![Element::Other(Token::Other("2".to_string()))]),
+    ]);
+    let (module, errors) = lowerer.lower(&[]);
+
+    assert_eq!(errors, vec![]);
+    assert_eq!(module.externs, vec!["sqrt".to_string()]);
+    assert_eq!(module.signatures, vec![("sqrt".to_string(), signature)]);
+
+    // The constant is passed as a double
+    let main = &module.functions[0];
+    assert_eq!(main.temps, vec![IrType::Double, IrType::Double]);
+    assert_eq!(main.blocks[0].instructions, vec![
+        Instruction::Line(1),
+        Instruction::Copy { dest: Temp(0), value: Value::Constant(2.0f64.to_bits() as i64) },
+        Instruction::Call {
+            dest: Temp(1),
+            callee: Callee { symbol: "sqrt".to_string(), is_extern: true },
+            arguments: vec![Value::Temp(Temp(0))],
+        },
+    ]);
+}
+
+#[test]
+fn module_calls() {
+    // use math.square
+    //
+    // fun main {
+    //     square(2)
+    // }
+    let signature = Signature {
+        id: "math.square".to_string(),
+        parameters: vec![Local { id: "a".to_string(), type_: IrType::Integer }],
+        return_type: IrType::Integer,
+    };
+    let signatures = Dict::from([("math.square".to_string(), signature.clone())]);
+
+    let mut lowerer = give_test_lowerer(signatures, vec![]);
+    lowerer.imports.insert("square".to_string(), "math.square".to_string());
+
+    lowerer.lower_elements(&[
+        Element::Other(Token::Other("square".to_string())),
+        Element::Parameters(vec![Element::Other(Token::Other("2".to_string()))]),
+    ]);
+    let (module, _errors) = lowerer.lower(&[]);
+
+    // The result has the return type of the function, not a big integer
+    assert_eq!(module.signatures, vec![("math.square".to_string(), signature)]);
+    assert_eq!(module.functions[0].temps, vec![IrType::Integer]);
+}
+
+#[cfg(test)]
+fn give_terminators(function: &Function) -> Vec<Terminator> {
+    function.blocks.iter().map(|block| block.terminator.clone()).collect()
+}
+
+#[test]
+fn nested_control_flow() {
+    // fun main(a: int) {
+    //     loop a < 10 {
+    //         if a == 5 {
+    //             break
+    //         }
+    //         f(a)
+    //     }
+    // }
+    let mut lowerer = give_test_lowerer(Dict::new(), vec![("a", IrType::Integer)]);
+    let other = |token: &str| Element::Other(Token::Other(token.to_string()));
+
+    lowerer.lower_elements(&[
+        Element::Other(Token::Loop),
+        other("a"), Element::Other(Token::LessThan), other("10"),
+        Element::Expression(vec![
+            Element::Other(Token::ConditionIf),
+            other("a"), Element::Other(Token::Equal), other("5"),
+            Element::Expression(vec![Element::Other(Token::LoopBreak)]),
+            other("f"), Element::Parameters(vec![other("a")]),
+        ]),
+    ]);
+    let (module, _errors) = lowerer.lower(&[]);
+
+    // The "break" leaves the loop from the condition, the loop goes back to
+    // its test after the condition
+    let main = &module.functions[0];
+    assert_eq!(give_terminators(main), vec![
+        Terminator::Jump(BlockId(1)),
+        Terminator::Branch { condition: Value::Temp(Temp(1)), then: BlockId(2), otherwise: BlockId(3) },
+        Terminator::Branch { condition: Value::Temp(Temp(3)), then: BlockId(4), otherwise: BlockId(5) },
+        Terminator::Return(None),
+        Terminator::Jump(BlockId(3)),
+        Terminator::Jump(BlockId(1)),
+        Terminator::Jump(BlockId(5)),
+    ]);
+    assert_eq!(main.blocks[5].instructions, vec![
+        Instruction::Line(1),
+        Instruction::Load { dest: Temp(4), local: LocalId(0), index: 0 },
+        Instruction::Call {
+            dest: Temp(5),
+            callee: Callee { symbol: "f".to_string(), is_extern: true },
+            arguments: vec![Value::Temp(Temp(4))],
+        },
+    ]);
+}
+
+#[test]
+fn else_if_chain() {
+    // fun main(a: int) {
+    //     if a == 1 { f(1) } else if a == 2 { f(2) } else { f(3) }
+    // }
+    let mut lowerer = give_test_lowerer(Dict::new(), vec![("a", IrType::Integer)]);
+    let other = |token: &str| Element::Other(Token::Other(token.to_string()));
+    let call = |argument: &str| Element::Expression(vec![
+        other("f"), Element::Parameters(vec![other(argument)]),
+    ]);
+
+    lowerer.lower_elements(&[
+        Element::Other(Token::ConditionIf),
+        other("a"), Element::Other(Token::Equal), other("1"),
+        call("1"),
+        Element::Other(Token::ConditionElse),
+        Element::Other(Token::ConditionIf),
+        other("a"), Element::Other(Token::Equal), other("2"),
+        call("2"),
+        Element::Other(Token::ConditionElse),
+        call("3"),
+    ]);
+    let (module, _errors) = lowerer.lower(&[]);
+
+    // Each body goes to the end block of the chain
+    assert_eq!(give_terminators(&module.functions[0]), vec![
+        Terminator::Branch { condition: Value::Temp(Temp(1)), then: BlockId(1), otherwise: BlockId(2) },
+        Terminator::Jump(BlockId(3)),
+        Terminator::Branch { condition: Value::Temp(Temp(4)), then: BlockId(4), otherwise: BlockId(5) },
+        Terminator::Return(None),
+        Terminator::Jump(BlockId(3)),
+        Terminator::Jump(BlockId(3)),
+    ]);
+}
+
+#[test]
+fn boolean_operators() {
+    // fun main(a: byte, b: byte) {
+    //     f(a and b, a or b)
+    // }
+    let mut lowerer = give_test_lowerer(Dict::new(), vec![("a", IrType::Byte), ("b", IrType::Byte)]);
+    let other = |token: &str| Element::Other(Token::Other(token.to_string()));
+
+    lowerer.lower_elements(&[
+        other("f"),
+        Element::Parameters(vec![
+            other("a"), other("and"), other("b"),
+            Element::Other(Token::Comma),
+            other("a"), other("or"), other("b"),
+        ]),
+    ]);
+    let (module, _errors) = lowerer.lower(&[]);
+
+    // The right operand is only tested when the left one does not give the
+    // result, both values are written in the same local
+    let main = &module.functions[0];
+    assert_eq!(main.locals.len(), 3);
+    assert_eq!(give_terminators(main), vec![
+        Terminator::Branch { condition: Value::Temp(Temp(0)), then: BlockId(4), otherwise: BlockId(2) },
+        Terminator::Jump(BlockId(3)),
+        Terminator::Jump(BlockId(3)),
+        Terminator::Branch { condition: Value::Temp(Temp(3)), then: BlockId(5), otherwise: BlockId(8) },
+        Terminator::Branch { condition: Value::Temp(Temp(1)), then: BlockId(1), otherwise: BlockId(2) },
+        Terminator::Jump(BlockId(7)),
+        Terminator::Jump(BlockId(7)),
+        Terminator::Return(None),
+        Terminator::Branch { condition: Value::Temp(Temp(4)), then: BlockId(5), otherwise: BlockId(6) },
+    ]);
+    assert_eq!(main.blocks[1].instructions, vec![
+        Instruction::Line(1),
+        Instruction::Store { local: LocalId(2), index: 0, value: Value::Constant(1) },
+    ]);
+    assert_eq!(main.blocks[7].instructions, vec![
+        Instruction::Line(1),
+        Instruction::Load { dest: Temp(5), local: LocalId(2), index: 0 },
+        Instruction::Call {
+            dest: Temp(6),
+            callee: Callee { symbol: "f".to_string(), is_extern: true },
+            arguments: vec![Value::Temp(Temp(2)), Value::Temp(Temp(5))],
+        },
+    ]);
+}
+
+#[test]
+fn call_arguments() {
+    // fun main(a: int) {
+    //     f(a, g(a + 1), 2)
+    // }
+    let mut lowerer = give_test_lowerer(Dict::new(), vec![("a", IrType::Integer)]);
+    let other = |token: &str| Element::Other(Token::Other(token.to_string()));
+
+    lowerer.lower_elements(&[
+        other("f"),
+        Element::Parameters(vec![
+            other("a"),
+            Element::Other(Token::Comma),
+            other("g"), Element::Parameters(vec![other("a"), Element::Other(Token::Plus), other("1")]),
+            Element::Other(Token::Comma),
+            other("2"),
+        ]),
+    ]);
+    let (module, _errors) = lowerer.lower(&[]);
+
+    // The arguments are computed from left to right, a call used as an
+    // argument before the call taking it
+    let call = |dest: usize, symbol: &str, arguments: Vec<Value>| Instruction::Call {
+        dest: Temp(dest),
+        callee: Callee { symbol: symbol.to_string(), is_extern: true },
+        arguments,
+    };
+
+    let main = &module.functions[0];
+    assert_eq!(main.temps, vec![
+        IrType::Integer,
+        IrType::Integer,
+        IrType::Integer,
+        IrType::BigInteger,
+        IrType::BigInteger,
+    ]);
+    assert_eq!(main.blocks[0].instructions, vec![
+        Instruction::Line(1),
+        Instruction::Load { dest: Temp(0), local: LocalId(0), index: 0 },
+        Instruction::Load { dest: Temp(1), local: LocalId(0), index: 0 },
+        Instruction::Binary {
+            dest: Temp(2),
+            operator: BinaryOperator::Add,
+            left: Value::Temp(Temp(1)),
+            right: Value::Constant(1),
+        },
+        call(3, "g", vec![Value::Temp(Temp(2))]),
+        call(4, "f", vec![Value::Temp(Temp(0)), Value::Temp(Temp(3)), Value::Constant(2)]),
+    ]);
+}
@@ -0,0 +1,660 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Typed intermediate representation between the parsed elements and the
+//! platform's compilers.
+//!
+//! The elements of a source file are lowered into a `Module` (see `lower`),
+//! some passes run on it (see `passes`), then a platform's compiler only
+//! translates the module to machine code.
+//!
+//! Each function is made of basic blocks. A block is a list of three-address
+//! instructions ended by one terminator, the only place where the control flow
+//! can change. The values computed by the instructions are stored in
+//! temporaries, each one is assigned only once. The variables are locals,
+//! read and written with `Load` and `Store` :
+//! ```text
+//! fun math.square(a: int): int
+//! block_0:
+//!     t0: int = load a
+//!     t1: int = mul t0, t0
+//!     ret t1
+//! ```
+
+pub mod check;
+pub mod expression;
+pub mod lower;
+pub mod passes;
+pub mod regalloc;
+
+use std::fmt;
+
+use jup::lang::elements::type_::Type;
+
+/// Temporary value, computed once by an instruction
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct Temp(pub usize);
+
+/// Index of a local in `Function::locals`
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct LocalId(pub usize);
+
+/// Identifier of a block in a function, kept when other blocks are removed
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct BlockId(pub usize);
+
+/// Type of the locals and temporaries
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum IrType {
+    Byte,
+    Integer,
+    BigInteger,
+    UnsignedByte,
+    UnsignedInteger,
+    UnsignedBigInteger,
+    Float,
+    Double,
+    /// Type of the elements and number of elements
+    Array(Box<IrType>, usize),
+    /// No value, for the functions returning nothing
+    Void,
+}
+
+impl IrType {
+    /// Converts a type from the parser. A static array has no length yet, it
+    /// is known once assigned.
+    ///
+    /// Returns `None` when the type is not supported
+    pub fn from_type(type_: &Type) -> Option<Self> {
+        match type_ {
+            Type::Byte => Some(IrType::Byte),
+            Type::Integer => Some(IrType::Integer),
+            Type::BigInteger => Some(IrType::BigInteger),
+            Type::Other(id) => IrType::from_cast_id(id),
+            Type::Array(type_, length) => Some(IrType::Array(
+                Box::new(IrType::from_type(type_)?),
+                *length
+            )),
+            Type::StaticArray(type_) => Some(IrType::Array(
+                Box::new(IrType::from_type(type_)?),
+                0
+            )),
+            Type::None => Some(IrType::Void),
+            _ => None,
+        }
+    }
+
+    /// Gives the type of a cast written like a call : `byte(a)`
+    pub fn from_cast_id(id: &str) -> Option<Self> {
+        match id {
+            "byte" => Some(IrType::Byte),
+            "int" => Some(IrType::Integer),
+            "bigint" => Some(IrType::BigInteger),
+            "ubyte" => Some(IrType::UnsignedByte),
+            "uint" => Some(IrType::UnsignedInteger),
+            "ubigint" => Some(IrType::UnsignedBigInteger),
+            "float" => Some(IrType::Float),
+            "double" => Some(IrType::Double),
+            _ => None,
+        }
+    }
+
+    /// Number of elements, one when it's not an array
+    pub fn length(&self) -> usize {
+        match self {
+            IrType::Array(_type, length) => *length,
+            _ => 1,
+        }
+    }
+
+    /// Type of the elements for an array, the type itself else
+    pub fn element_type(&self) -> &IrType {
+        match self {
+            IrType::Array(element_type, _length) => element_type,
+            type_ => type_,
+        }
+    }
+
+    /// Number of bytes taken in memory by a value of the type, by all the
+    /// elements for an array
+    pub fn size(&self) -> usize {
+        match self {
+            IrType::Byte | IrType::UnsignedByte => 1,
+            IrType::Integer | IrType::UnsignedInteger | IrType::Float => 4,
+            IrType::BigInteger | IrType::UnsignedBigInteger | IrType::Double => 8,
+            IrType::Array(element_type, length) => element_type.size() * length,
+            IrType::Void => 0,
+        }
+    }
+
+    /// Smallest and biggest values of a number type
+    pub fn range(&self) -> Option<(i128, i128)> {
+        match self {
+            IrType::Byte => Some((i8::MIN as i128, i8::MAX as i128)),
+            IrType::Integer => Some((i32::MIN as i128, i32::MAX as i128)),
+            IrType::BigInteger => Some((i64::MIN as i128, i64::MAX as i128)),
+            IrType::UnsignedByte => Some((0, u8::MAX as i128)),
+            IrType::UnsignedInteger => Some((0, u32::MAX as i128)),
+            IrType::UnsignedBigInteger => Some((0, u64::MAX as i128)),
+            IrType::Float | IrType::Double | IrType::Array(..) | IrType::Void => None,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, IrType::Float | IrType::Double)
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            IrType::UnsignedByte | IrType::UnsignedInteger | IrType::UnsignedBigInteger
+        )
+    }
+
+    /// Can a value of the type `other` be given to this type without an 
+    /// explicit cast ? Only the conversions to a wider number type of the
+    /// same signedness are implicit, and from a float to a double
+    pub fn can_hold(&self, other: &IrType) -> bool {
+        match (self.range(), other.range()) {
+            (Some((min, max)), Some((other_min, other_max))) => {
+                self.is_unsigned() == other.is_unsigned()
+                    && min <= other_min
+                    && other_max <= max
+            }
+            _ => self.is_float() && other.is_float() && self.size() >= other.size(),
+        }
+    }
+
+    /// Gives the constant as it is once cast to this number type, the bits
+    /// that do not fit are removed. An unsigned big integer keeps its bits
+    pub fn wrap(&self, constant: i64) -> i64 {
+        match self {
+            IrType::Byte => constant as i8 as i64,
+            IrType::Integer => constant as i32 as i64,
+            IrType::UnsignedByte => constant as u8 as i64,
+            IrType::UnsignedInteger => constant as u32 as i64,
+            _ => constant,
+        }
+    }
+}
+
+/// Operand of an instruction
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Value {
+    Temp(Temp),
+    /// A floating-point constant is written with the bits of its value, only
+    /// copied to a temporary of its type
+    Constant(i64),
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    /// Remainder of the division, of the sign of the left operand
+    Modulo,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    /// Keeps the sign of the left operand
+    ShiftRight,
+    UnsignedDivide,
+    UnsignedModulo,
+    /// Fills the left bits with zeros
+    UnsignedShiftRight,
+}
+
+impl BinaryOperator {
+    /// Gives the operator for the unsigned operands
+    pub fn to_unsigned(self) -> Self {
+        match self {
+            BinaryOperator::Divide => BinaryOperator::UnsignedDivide,
+            BinaryOperator::Modulo => BinaryOperator::UnsignedModulo,
+            BinaryOperator::ShiftRight => BinaryOperator::UnsignedShiftRight,
+            operator => operator,
+        }
+    }
+
+    /// Can the operation fail at runtime, for a division by zero ?
+    pub fn is_division(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Divide
+                | BinaryOperator::Modulo
+                | BinaryOperator::UnsignedDivide
+                | BinaryOperator::UnsignedModulo
+        )
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Condition {
+    Equal,
+    NotEqual,
+    LessThan,
+    MoreThan,
+    LessThanOrEqual,
+    MoreThanOrEqual,
+    /// Unsigned "less than"
+    Below,
+    /// Unsigned "more than"
+    Above,
+    BelowOrEqual,
+    AboveOrEqual,
+}
+
+impl Condition {
+    /// Gives the condition for the unsigned operands
+    pub fn to_unsigned(self) -> Self {
+        match self {
+            Condition::LessThan => Condition::Below,
+            Condition::MoreThan => Condition::Above,
+            Condition::LessThanOrEqual => Condition::BelowOrEqual,
+            Condition::MoreThanOrEqual => Condition::AboveOrEqual,
+            condition => condition,
+        }
+    }
+}
+
+/// Function called by a `Call` instruction
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Callee {
+    /// Mangled identifier, see `lower::Lowerer::give_mangled_id()`
+    pub symbol: String,
+    /// Not defined in the current module, so it's an external symbol
+    pub is_extern: bool,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Instruction {
+    /// `dest = value`
+    Copy { dest: Temp, value: Value },
+    /// `dest = left <operator> right`
+    Binary { dest: Temp, operator: BinaryOperator, left: Value, right: Value },
+    /// `dest = left <condition> right`, one when true and zero when false
+    Compare { dest: Temp, condition: Condition, left: Value, right: Value },
+    /// `dest = local[index]`, the index is zero when it's not an array
+    Load { dest: Temp, local: LocalId, index: usize },
+    /// `local[index] = value`
+    Store { local: LocalId, index: usize, value: Value },
+    /// `dest = value`, converted to the type of `dest`
+    Cast { dest: Temp, value: Value },
+    /// `dest = callee(arguments)`
+    Call { dest: Temp, callee: Callee, arguments: Vec<Value> },
+    /// Assembly code written in the source file
+    Assembly(String),
+    /// Line of the source file where the next instructions are written, for
+    /// the diagnostics. Nothing is generated
+    Line(usize),
+}
+
+impl Instruction {
+    /// Gives the temporary computed by the instruction
+    pub fn dest(&self) -> Option<Temp> {
+        match self {
+            Instruction::Copy { dest, .. }
+            | Instruction::Binary { dest, .. }
+            | Instruction::Compare { dest, .. }
+            | Instruction::Load { dest, .. }
+            | Instruction::Cast { dest, .. }
+            | Instruction::Call { dest, .. } => Some(*dest),
+            Instruction::Store { .. }
+            | Instruction::Assembly(_)
+            | Instruction::Line(_) => None,
+        }
+    }
+
+    /// Gives the values read by the instruction
+    pub fn values(&self) -> Vec<Value> {
+        match self {
+            Instruction::Copy { value, .. }
+            | Instruction::Cast { value, .. } => vec![*value],
+            Instruction::Binary { left, right, .. }
+            | Instruction::Compare { left, right, .. } => vec![*left, *right],
+            Instruction::Load { .. }
+            | Instruction::Assembly(_)
+            | Instruction::Line(_) => vec![],
+            Instruction::Store { value, .. } => vec![*value],
+            Instruction::Call { arguments, .. } => arguments.clone(),
+        }
+    }
+
+    /// Same as `values()` but to modify them
+    pub fn values_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Instruction::Copy { value, .. }
+            | Instruction::Cast { value, .. } => vec![value],
+            Instruction::Binary { left, right, .. }
+            | Instruction::Compare { left, right, .. } => vec![left, right],
+            Instruction::Load { .. }
+            | Instruction::Assembly(_)
+            | Instruction::Line(_) => vec![],
+            Instruction::Store { value, .. } => vec![value],
+            Instruction::Call { arguments, .. } => arguments.iter_mut().collect(),
+        }
+    }
+
+    /// Can the instruction be removed when its temporary is never used ?
+    ///
+    /// A division or a modulo is kept because it can fail at runtime
+    pub fn is_pure(&self) -> bool {
+        match self {
+            Instruction::Copy { .. }
+            | Instruction::Compare { .. }
+            | Instruction::Load { .. }
+            | Instruction::Cast { .. } => true,
+            Instruction::Binary { operator, .. } => !operator.is_division(),
+            Instruction::Store { .. }
+            | Instruction::Call { .. }
+            | Instruction::Assembly(_)
+            | Instruction::Line(_) => false,
+        }
+    }
+}
+
+/// Ends a block
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Terminator {
+    Jump(BlockId),
+    /// Jumps to `then` when `condition` is not zero, to `otherwise` else
+    Branch { condition: Value, then: BlockId, otherwise: BlockId },
+    /// Returns from the function, `None` returns zero
+    Return(Option<Value>),
+}
+
+impl Terminator {
+    pub fn values(&self) -> Vec<Value> {
+        match self {
+            Terminator::Jump(_) | Terminator::Return(None) => vec![],
+            Terminator::Branch { condition, .. } => vec![*condition],
+            Terminator::Return(Some(value)) => vec![*value],
+        }
+    }
+
+    pub fn values_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Terminator::Jump(_) | Terminator::Return(None) => vec![],
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Return(Some(value)) => vec![value],
+        }
+    }
+
+    /// Blocks where the control flow can go after this terminator
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch { then, otherwise, .. } => vec![*then, *otherwise],
+            Terminator::Return(_) => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Block {
+    pub id: BlockId,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+/// A variable or a parameter of a function
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Local {
+    pub id: String,
+    pub type_: IrType,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Function {
+    /// Identifier written in the source file
+    pub id: String,
+    /// Mangled identifier, see `lower::Lowerer::give_mangled_id()`
+    pub symbol: String,
+    /// Locals receiving the passed parameters, in order
+    pub parameters: Vec<LocalId>,
+    pub return_type: IrType,
+    pub locals: Vec<Local>,
+    /// Type of each temporary
+    pub temps: Vec<IrType>,
+    /// The first block is the entry of the function
+    pub blocks: Vec<Block>,
+}
+
+impl Function {
+    /// Number of times the temporary is read
+    pub fn count_uses(&self, temp: Temp) -> usize {
+        let mut n_uses = 0;
+
+        for block in &self.blocks {
+            n_uses += block.instructions
+                .iter()
+                .flat_map(|instruction| instruction.values())
+                .chain(block.terminator.values())
+                .filter(|value| value == &Value::Temp(temp))
+                .count();
+        }
+
+        n_uses
+    }
+
+    pub fn signature(&self) -> Signature {
+        Signature {
+            id: self.id.clone(),
+            parameters: self.parameters
+                .iter()
+                .map(|parameter| self.locals[parameter.0].clone())
+                .collect(),
+            return_type: self.return_type.clone(),
+        }
+    }
+}
+
+/// Passed parameters and return type of a function, to type its calls
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Signature {
+    /// Identifier written in the source file
+    pub id: String,
+    pub parameters: Vec<Local>,
+    pub return_type: IrType,
+}
+
+/// IR of a source file
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Module {
+    /// Named from the source file's path
+    pub id: String,
+    pub functions: Vec<Function>,
+    /// Symbols of the called functions defined in other object files
+    pub externs: Vec<String>,
+    /// Signatures of the external functions declared with `extern` or
+    /// defined in another module, from their symbol
+    pub signatures: Vec<(String, Signature)>,
+    /// Functions imported with `use`, as their identifier in the source file
+    /// and their qualified identifier
+    pub imports: Vec<(String, String)>,
+    /// Assembly code written outside of the functions
+    pub assembly: Vec<String>,
+}
+
+// Text form of the IR ---------------------------------------------------------
+
+impl fmt::Display for IrType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IrType::Byte => write!(f, "byte"),
+            IrType::Integer => write!(f, "int"),
+            IrType::BigInteger => write!(f, "bigint"),
+            IrType::UnsignedByte => write!(f, "ubyte"),
+            IrType::UnsignedInteger => write!(f, "uint"),
+            IrType::UnsignedBigInteger => write!(f, "ubigint"),
+            IrType::Float => write!(f, "float"),
+            IrType::Double => write!(f, "double"),
+            IrType::Array(type_, length) => write!(f, "{}[{}]", type_, length),
+            IrType::Void => write!(f, "void"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Temp(temp) => write!(f, "t{}", temp.0),
+            Value::Constant(constant) => write!(f, "{}", constant),
+        }
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "block_{}", self.0)
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryOperator::Add => write!(f, "add"),
+            BinaryOperator::Subtract => write!(f, "sub"),
+            BinaryOperator::Multiply => write!(f, "mul"),
+            BinaryOperator::Divide => write!(f, "div"),
+            BinaryOperator::Modulo => write!(f, "mod"),
+            BinaryOperator::BitwiseAnd => write!(f, "and"),
+            BinaryOperator::BitwiseOr => write!(f, "or"),
+            BinaryOperator::BitwiseXor => write!(f, "xor"),
+            BinaryOperator::ShiftLeft => write!(f, "shl"),
+            BinaryOperator::ShiftRight => write!(f, "shr"),
+            BinaryOperator::UnsignedDivide => write!(f, "udiv"),
+            BinaryOperator::UnsignedModulo => write!(f, "umod"),
+            BinaryOperator::UnsignedShiftRight => write!(f, "ushr"),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Equal => write!(f, "eq"),
+            Condition::NotEqual => write!(f, "ne"),
+            Condition::LessThan => write!(f, "lt"),
+            Condition::MoreThan => write!(f, "gt"),
+            Condition::LessThanOrEqual => write!(f, "le"),
+            Condition::MoreThanOrEqual => write!(f, "ge"),
+            Condition::Below => write!(f, "ult"),
+            Condition::Above => write!(f, "ugt"),
+            Condition::BelowOrEqual => write!(f, "ule"),
+            Condition::AboveOrEqual => write!(f, "uge"),
+        }
+    }
+}
+
+impl Function {
+    fn fmt_instruction(&self, f: &mut fmt::Formatter, instruction: &Instruction) -> fmt::Result {
+        let local = |local: &LocalId| &self.locals[local.0].id;
+        let index = |index: &usize| if *index == 0 { String::new() } else { format!("[{}]", index) };
+
+        if let Some(dest) = instruction.dest() {
+            write!(f, "t{}: {} = ", dest.0, self.temps[dest.0])?;
+        }
+
+        match instruction {
+            Instruction::Copy { value, .. } => write!(f, "{}", value),
+            Instruction::Binary { operator, left, right, .. } => {
+                write!(f, "{} {}, {}", operator, left, right)
+            }
+            Instruction::Compare { condition, left, right, .. } => {
+                write!(f, "cmp {} {}, {}", condition, left, right)
+            }
+            Instruction::Load { local: id, index: i, .. } => {
+                write!(f, "load {}{}", local(id), index(i))
+            }
+            Instruction::Store { local: id, index: i, value } => {
+                write!(f, "store {}{}, {}", local(id), index(i), value)
+            }
+            Instruction::Cast { value, .. } => write!(f, "cast {}", value),
+            Instruction::Call { callee, arguments, .. } => {
+                write!(f, "call {}(", callee.symbol)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+            Instruction::Assembly(code) => write!(f, "@ {}", code),
+            Instruction::Line(line) => write!(f, "line {}", line),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jmp {}", target),
+            Terminator::Branch { condition, then, otherwise } => {
+                write!(f, "br {}, {}, {}", condition, then, otherwise)
+            }
+            Terminator::Return(None) => write!(f, "ret"),
+            Terminator::Return(Some(value)) => write!(f, "ret {}", value),
+        }
+    }
+}
+
+/// Written after the function's identifier : "(a: int): int"
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", parameter.id, parameter.type_)?;
+        }
+        write!(f, "): {}", self.return_type)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "fun {}{}", self.symbol, self.signature())?;
+
+        for block in &self.blocks {
+            writeln!(f, "{}:", block.id)?;
+            for instruction in &block.instructions {
+                write!(f, "    ")?;
+                self.fmt_instruction(f, instruction)?;
+                writeln!(f)?;
+            }
+            writeln!(f, "    {}", block.terminator)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "module {}", self.id)?;
+
+        for symbol in &self.externs {
+            match self.signatures.iter().find(|(signed, _signature)| signed == symbol) {
+                Some((_symbol, signature)) => writeln!(f, "extern {}{}", symbol, signature)?,
+                None => writeln!(f, "extern {}", symbol)?,
+            }
+        }
+
+        for code in &self.assembly {
+            writeln!(f, "@ {}", code)?;
+        }
+
+        for function in &self.functions {
+            writeln!(f)?;
+            write!(f, "{}", function)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,443 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Analysis and optimization passes on the IR, run before a platform's
+//! compiler translates it
+
+use std::collections::{
+    HashMap as Dict,
+    HashSet,
+};
+
+use crate::ir::{
+    BinaryOperator,
+    Block,
+    BlockId,
+    Condition,
+    Function,
+    Instruction,
+    IrType,
+    Module,
+    Temp,
+    Terminator,
+    Value,
+};
+
+/// Runs all the passes on each function of the module, and the optional
+/// optimizations when `is_optimized`
+pub fn run(module: &mut Module, is_optimized: bool) {
+    for function in &mut module.functions {
+        if is_optimized {
+            replace_tail_calls(function);
+        }
+
+        fold_constants(function);
+        remove_unreachable_blocks(function);
+        remove_unused_temps(function);
+    }
+}
+
+/// Replaces the operations on constants by their result, and the temporaries
+/// holding a constant by the constant itself. A branch on a constant becomes a
+/// jump.
+///
+/// The divisions and the modulos are not folded, their behaviour for a
+/// division by zero is the one at runtime. The floating-point values are not
+/// folded too, their constants are only the bits of their value. Neither are
+/// the unsigned big integers above the biggest big integer, their constant
+/// would be taken as a negative value
+pub fn fold_constants(function: &mut Function) {
+    let mut constants: Dict<Temp, i64> = Dict::new();
+    let temps = &function.temps;
+
+    let is_foldable = |dest: &Temp, constant: i64| match temps[dest.0] {
+        IrType::Float | IrType::Double => false,
+        IrType::UnsignedBigInteger => constant >= 0,
+        _ => true,
+    };
+
+    // A temporary can be used in a block before the one computing it, so it's
+    // done again until nothing changes
+    loop {
+        let mut is_changed = false;
+
+        for block in &mut function.blocks {
+            for instruction in &mut block.instructions {
+                is_changed |= replace_constants(instruction.values_mut(), &constants);
+
+                let folded = match instruction {
+                    Instruction::Copy { dest, value: Value::Constant(constant) }
+                        if is_foldable(dest, *constant) =>
+                    {
+                        constants.insert(*dest, *constant);
+                        None
+                    }
+                    Instruction::Binary {
+                        dest,
+                        operator,
+                        left: Value::Constant(left),
+                        right: Value::Constant(right),
+                    } => fold_binary(*operator, *left, *right).map(|constant| (*dest, constant)),
+                    Instruction::Compare {
+                        dest,
+                        condition,
+                        left: Value::Constant(left),
+                        right: Value::Constant(right),
+                    } => Some((*dest, fold_comparison(*condition, *left, *right) as i64)),
+                    Instruction::Cast { dest, value: Value::Constant(constant) }
+                        if !temps[dest.0].is_float() =>
+                    {
+                        Some((*dest, temps[dest.0].wrap(*constant)))
+                    }
+                    _ => None,
+                };
+
+                if let Some((dest, constant)) = folded {
+                    *instruction = Instruction::Copy { dest, value: Value::Constant(constant) };
+                    if is_foldable(&dest, constant) {
+                        constants.insert(dest, constant);
+                    }
+                    is_changed = true;
+                }
+            }
+
+            is_changed |= replace_constants(block.terminator.values_mut(), &constants);
+        }
+
+        if !is_changed {
+            break;
+        }
+    }
+
+    for block in &mut function.blocks {
+        if let Terminator::Branch { condition: Value::Constant(constant), then, otherwise } = block.terminator {
+            block.terminator = Terminator::Jump(if constant != 0 { then } else { otherwise });
+        }
+    }
+}
+
+/// Returns if a value was replaced
+fn replace_constants(values: Vec<&mut Value>, constants: &Dict<Temp, i64>) -> bool {
+    let mut is_replaced = false;
+
+    for value in values {
+        if let Value::Temp(temp) = value {
+            if let Some(constant) = constants.get(temp) {
+                *value = Value::Constant(*constant);
+                is_replaced = true;
+            }
+        }
+    }
+
+    is_replaced
+}
+
+fn fold_binary(operator: BinaryOperator, left: i64, right: i64) -> Option<i64> {
+    match operator {
+        BinaryOperator::Add => Some(left.wrapping_add(right)),
+        BinaryOperator::Subtract => Some(left.wrapping_sub(right)),
+        BinaryOperator::Multiply => Some(left.wrapping_mul(right)),
+        BinaryOperator::Divide
+        | BinaryOperator::Modulo
+        | BinaryOperator::UnsignedDivide
+        | BinaryOperator::UnsignedModulo => None,
+        BinaryOperator::BitwiseAnd => Some(left & right),
+        BinaryOperator::BitwiseOr => Some(left | right),
+        BinaryOperator::BitwiseXor => Some(left ^ right),
+        // The count is taken modulo 64, like the processor does
+        BinaryOperator::ShiftLeft => Some(left.wrapping_shl(right as u32)),
+        BinaryOperator::ShiftRight => Some(left.wrapping_shr(right as u32)),
+        BinaryOperator::UnsignedShiftRight => Some((left as u64).wrapping_shr(right as u32) as i64),
+    }
+}
+
+fn fold_comparison(condition: Condition, left: i64, right: i64) -> bool {
+    match condition {
+        Condition::Equal => left == right,
+        Condition::NotEqual => left != right,
+        Condition::LessThan => left < right,
+        Condition::MoreThan => left > right,
+        Condition::LessThanOrEqual => left <= right,
+        Condition::MoreThanOrEqual => left >= right,
+        Condition::Below => (left as u64) < (right as u64),
+        Condition::Above => (left as u64) > (right as u64),
+        Condition::BelowOrEqual => (left as u64) <= (right as u64),
+        Condition::AboveOrEqual => (left as u64) >= (right as u64),
+    }
+}
+
+/// Removes the blocks that cannot be reached from the entry block
+pub fn remove_unreachable_blocks(function: &mut Function) {
+    let entry = match function.blocks.first() {
+        Some(entry) => entry.id,
+        None => return,
+    };
+
+    let mut reached: HashSet<BlockId> = HashSet::new();
+    let mut to_visit: Vec<BlockId> = vec![entry];
+
+    while let Some(id) = to_visit.pop() {
+        if !reached.insert(id) {
+            continue;
+        }
+
+        let block: &Block = function.blocks
+            .iter()
+            .find(|block| block.id == id)
+            .unwrap();
+
+        to_visit.extend(block.terminator.successors());
+    }
+
+    function.blocks.retain(|block| reached.contains(&block.id));
+}
+
+/// Removes the instructions computing a temporary never used, when they have
+/// no other effect
+pub fn remove_unused_temps(function: &mut Function) {
+    loop {
+        let used: HashSet<Temp> = function.blocks
+            .iter()
+            .flat_map(|block| {
+                block.instructions
+                    .iter()
+                    .flat_map(|instruction| instruction.values())
+                    .chain(block.terminator.values())
+            })
+            .filter_map(|value| match value {
+                Value::Temp(temp) => Some(temp),
+                Value::Constant(_) => None,
+            })
+            .collect();
+
+        let mut is_changed = false;
+
+        for block in &mut function.blocks {
+            let n_instructions = block.instructions.len();
+
+            block.instructions.retain(|instruction| match instruction.dest() {
+                Some(dest) => !instruction.is_pure() || used.contains(&dest),
+                None => true,
+            });
+
+            is_changed |= block.instructions.len() != n_instructions;
+        }
+
+        if !is_changed {
+            break;
+        }
+    }
+}
+
+/// Replaces each call of the function to itself in tail position, when its
+/// value is directly returned, by a jump to its first block : the passed
+/// parameters are stored in the parameters' locals instead. The function 
+/// keeps its frame, so a deep recursion does not overflow the stack.
+///
+/// The passed parameters are all computed before the call, so storing one 
+/// does not change the other ones
+pub fn replace_tail_calls(function: &mut Function) {
+    let entry = match function.blocks.first() {
+        Some(block) => block.id,
+        None => return,
+    };
+
+    for block in &mut function.blocks {
+        // A call with a wrong number of arguments is kept, to be reported by
+        // the type checking
+        let is_tail_call = match (block.instructions.last(), &block.terminator) {
+            (
+                Some(Instruction::Call { dest, callee, arguments }),
+                Terminator::Return(returned),
+            ) if callee.symbol == function.symbol
+                && !callee.is_extern
+                && arguments.len() == function.parameters.len() => match returned {
+                Some(Value::Temp(temp)) => temp == dest,
+                Some(Value::Constant(_)) => false,
+                None => true,
+            },
+            _ => false,
+        };
+
+        if !is_tail_call {
+            continue;
+        }
+
+        let arguments = match block.instructions.pop() {
+            Some(Instruction::Call { arguments, .. }) => arguments,
+            _ => unreachable!(),
+        };
+
+        for (local, value) in function.parameters.iter().zip(arguments) {
+            block.instructions.push(Instruction::Store { local: *local, index: 0, value });
+        }
+        block.terminator = Terminator::Jump(entry);
+    }
+}
+
+#[test]
+fn constant_folding() {
+    use crate::ir::{Local, LocalId};
+
+    // if { 2 + 3 } < 10 { a = 1 } else { a = 2 }
+    let mut function = Function {
+        id: "main".to_string(),
+        symbol: "main".to_string(),
+        parameters: vec![],
+        return_type: IrType::Void,
+        locals: vec![Local { id: "a".to_string(), type_: IrType::Integer }],
+        temps: vec![IrType::Integer, IrType::Byte],
+        blocks: vec![
+            Block {
+                id: BlockId(0),
+                instructions: vec![
+                    Instruction::Binary {
+                        dest: Temp(0),
+                        operator: BinaryOperator::Add,
+                        left: Value::Constant(2),
+                        right: Value::Constant(3),
+                    },
+                    Instruction::Compare {
+                        dest: Temp(1),
+                        condition: Condition::LessThan,
+                        left: Value::Temp(Temp(0)),
+                        right: Value::Constant(10),
+                    },
+                ],
+                terminator: Terminator::Branch {
+                    condition: Value::Temp(Temp(1)),
+                    then: BlockId(1),
+                    otherwise: BlockId(2),
+                },
+            },
+            Block {
+                id: BlockId(1),
+                instructions: vec![
+                    Instruction::Store { local: LocalId(0), index: 0, value: Value::Constant(1) },
+                ],
+                terminator: Terminator::Return(Some(Value::Temp(Temp(0)))),
+            },
+            Block {
+                id: BlockId(2),
+                instructions: vec![
+                    Instruction::Store { local: LocalId(0), index: 0, value: Value::Constant(2) },
+                ],
+                terminator: Terminator::Return(None),
+            },
+        ],
+    };
+
+    fold_constants(&mut function);
+    remove_unreachable_blocks(&mut function);
+    remove_unused_temps(&mut function);
+
+    assert_eq!(function.blocks.len(), 2);
+    assert!(function.blocks[0].instructions.is_empty());
+    assert_eq!(function.blocks[0].terminator, Terminator::Jump(BlockId(1)));
+    assert_eq!(function.blocks[1].terminator, Terminator::Return(Some(Value::Constant(5))));
+}
+
+#[test]
+fn big_unsigned_constant() {
+    use crate::ir::{Local, LocalId};
+
+    // let a: ubigint = 18446744073709551615
+    let mut function = Function {
+        id: "main".to_string(),
+        symbol: "main".to_string(),
+        parameters: vec![],
+        return_type: IrType::Void,
+        locals: vec![Local { id: "a".to_string(), type_: IrType::UnsignedBigInteger }],
+        temps: vec![IrType::UnsignedBigInteger],
+        blocks: vec![
+            Block {
+                id: BlockId(0),
+                instructions: vec![
+                    Instruction::Copy { dest: Temp(0), value: Value::Constant(-1) },
+                    Instruction::Store { local: LocalId(0), index: 0, value: Value::Temp(Temp(0)) },
+                ],
+                terminator: Terminator::Return(None),
+            },
+        ],
+    };
+    let blocks = function.blocks.clone();
+
+    // The stored value is not the negative constant
+    fold_constants(&mut function);
+    assert_eq!(function.blocks, blocks);
+}
+
+#[test]
+fn tail_call() {
+    use crate::ir::{Callee, IrType, Local, LocalId};
+
+    // fun count(n: int): int { if n == 0 { ret 0 } ret count({ n - 1 }) }
+    let mut function = Function {
+        id: "count".to_string(),
+        symbol: "count".to_string(),
+        parameters: vec![LocalId(0)],
+        return_type: IrType::Integer,
+        locals: vec![Local { id: "n".to_string(), type_: IrType::Integer }],
+        temps: vec![IrType::Integer, IrType::Byte, IrType::Integer, IrType::Integer],
+        blocks: vec![
+            Block {
+                id: BlockId(0),
+                instructions: vec![
+                    Instruction::Load { dest: Temp(0), local: LocalId(0), index: 0 },
+                    Instruction::Compare {
+                        dest: Temp(1),
+                        condition: Condition::Equal,
+                        left: Value::Temp(Temp(0)),
+                        right: Value::Constant(0),
+                    },
+                ],
+                terminator: Terminator::Branch {
+                    condition: Value::Temp(Temp(1)),
+                    then: BlockId(1),
+                    otherwise: BlockId(2),
+                },
+            },
+            Block {
+                id: BlockId(1),
+                instructions: vec![],
+                terminator: Terminator::Return(Some(Value::Constant(0))),
+            },
+            Block {
+                id: BlockId(2),
+                instructions: vec![
+                    Instruction::Binary {
+                        dest: Temp(2),
+                        operator: BinaryOperator::Subtract,
+                        left: Value::Temp(Temp(0)),
+                        right: Value::Constant(1),
+                    },
+                    Instruction::Call {
+                        dest: Temp(3),
+                        callee: Callee { symbol: "count".to_string(), is_extern: false },
+                        arguments: vec![Value::Temp(Temp(2))],
+                    },
+                ],
+                terminator: Terminator::Return(Some(Value::Temp(Temp(3)))),
+            },
+        ],
+    };
+
+    // Not replaced with a missing argument
+    let mut wrong_call = function.clone();
+    if let Instruction::Call { arguments, .. } = &mut wrong_call.blocks[2].instructions[1] {
+        arguments.clear();
+    }
+    replace_tail_calls(&mut wrong_call);
+    assert!(matches!(wrong_call.blocks[2].instructions[1], Instruction::Call { .. }));
+
+    replace_tail_calls(&mut function);
+
+    assert_eq!(function.blocks[2].instructions[1], Instruction::Store {
+        local: LocalId(0),
+        index: 0,
+        value: Value::Temp(Temp(2)),
+    });
+    assert_eq!(function.blocks[2].terminator, Terminator::Jump(BlockId(0)));
+    assert_eq!(function.blocks[1].terminator, Terminator::Return(Some(Value::Constant(0))));
+}
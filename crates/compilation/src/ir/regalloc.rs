@@ -0,0 +1,417 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Linear scan register allocation, run by a platform's compiler on each
+//! function of the IR.
+//!
+//! The live interval of each temporary and local is computed from a liveness
+//! analysis on the blocks, written one after the other. The intervals are
+//! then walked by their start : a free register is given to an interval, or
+//! when there is none, the interval ending the latest is kept in the stack.
+//!
+//! A value living across a call only gets a callee-saved register, so the
+//! called function keeps it. The function saves and restores the callee-saved
+//! registers it uses
+
+use std::collections::{
+    HashMap as Dict,
+    HashSet,
+};
+
+use crate::ir::{
+    Block,
+    Function,
+    Instruction,
+    IrType,
+    LocalId,
+    Temp,
+    Value,
+};
+
+/// A value of a function : a temporary or a local
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Variable {
+    Temp(Temp),
+    Local(LocalId),
+}
+
+/// Where a variable is for the whole function
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Location<R> {
+    Register(R),
+    /// Position from the base pointer, set when the frame is laid out
+    Stack(usize),
+}
+
+/// Registers which can be given to the variables, the ones used by the
+/// platform's compiler for its own needs are not given
+pub struct Registers<R> {
+    /// Kept by the called functions
+    pub callee_saved: Vec<R>,
+    /// Can be modified by the called functions
+    pub caller_saved: Vec<R>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Allocation<R> {
+    /// Location of each temporary, by `Temp`
+    pub temps: Vec<Location<R>>,
+    /// Location of each local, by `LocalId`
+    pub locals: Vec<Location<R>>,
+    /// Callee-saved registers used, to save and restore by the function
+    pub saved_registers: Vec<R>,
+}
+
+/// Positions where a variable is live, in the order of the blocks. The
+/// function's entry is the position zero
+#[derive(Debug)]
+struct Interval {
+    variable: Variable,
+    start: usize,
+    end: usize,
+    /// Lives across a call or is a parameter, so a caller-saved register
+    /// cannot be given
+    needs_callee_saved: bool,
+}
+
+/// Gives a location to each temporary and local of the function.
+///
+/// The arrays and the floating-point values are always in the stack. Assembly
+/// code written in a function can use any register and read the locals in the
+/// stack, so nothing of such a function is kept in registers
+pub fn allocate<R: Copy + Eq>(function: &Function, registers: &Registers<R>) -> Allocation<R> {
+    let mut allocation = Allocation {
+        temps: vec![Location::Stack(0); function.temps.len()],
+        locals: vec![Location::Stack(0); function.locals.len()],
+        saved_registers: vec![],
+    };
+
+    let has_assembly = function.blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .any(|instruction| matches!(instruction, Instruction::Assembly(_)));
+
+    if has_assembly {
+        return allocation;
+    }
+
+    let mut free_callee_saved: Vec<R> = registers.callee_saved.clone();
+    let mut free_caller_saved: Vec<R> = registers.caller_saved.clone();
+    let mut active: Vec<(Interval, R)> = vec![];
+    let mut assigned: Dict<Variable, R> = Dict::new();
+
+    for interval in give_intervals(function) {
+        // Frees the registers of the intervals ended before this one. An
+        // operand of an instruction and its result never have the same
+        // register
+        active.retain(|(active_interval, register)| {
+            if active_interval.end >= interval.start {
+                return true;
+            }
+
+            if registers.callee_saved.contains(register) {
+                free_callee_saved.push(*register);
+            } else {
+                free_caller_saved.push(*register);
+            }
+            false
+        });
+
+        let free_register = if !interval.needs_callee_saved && !free_caller_saved.is_empty() {
+            Some(free_caller_saved.remove(0))
+        } else if !free_callee_saved.is_empty() {
+            Some(free_callee_saved.remove(0))
+        } else {
+            None
+        };
+
+        if let Some(register) = free_register {
+            assigned.insert(interval.variable, register);
+            active.push((interval, register));
+            continue;
+        }
+
+        // No free register, the interval ending the latest gives its register
+        // when it ends after this one
+        let spilled = active.iter()
+            .enumerate()
+            .filter(|(_i, (active_interval, register))| {
+                active_interval.end > interval.end
+                    && (!interval.needs_callee_saved || registers.callee_saved.contains(register))
+            })
+            .max_by_key(|(_i, (active_interval, _register))| active_interval.end)
+            .map(|(i, _active)| i);
+
+        if let Some(i_spilled) = spilled {
+            let (spilled, register) = active.remove(i_spilled);
+            assigned.remove(&spilled.variable);
+
+            assigned.insert(interval.variable, register);
+            active.push((interval, register));
+        }
+    }
+
+    for (variable, register) in &assigned {
+        match variable {
+            Variable::Temp(temp) => allocation.temps[temp.0] = Location::Register(*register),
+            Variable::Local(local) => allocation.locals[local.0] = Location::Register(*register),
+        }
+    }
+
+    allocation.saved_registers = registers.callee_saved
+        .iter()
+        .filter(|register| assigned.values().any(|assigned| assigned == *register))
+        .copied()
+        .collect();
+
+    allocation
+}
+
+/// Gives the live interval of each variable which can be in a register,
+/// sorted by their start
+fn give_intervals(function: &Function) -> Vec<Interval> {
+    let liveness = give_liveness(function);
+
+    let mut ranges: Dict<Variable, (usize, usize)> = Dict::new();
+    let mut extend = |variable: Variable, position: usize| {
+        let range = ranges.entry(variable).or_insert((position, position));
+        range.0 = range.0.min(position);
+        range.1 = range.1.max(position);
+    };
+
+    // The parameters are given at the function's entry
+    for parameter in &function.parameters {
+        if is_in_register(function, *parameter) {
+            extend(Variable::Local(*parameter), 0);
+        }
+    }
+
+    let mut calls: Vec<usize> = vec![];
+    let mut position = 0;
+
+    for (i_block, block) in function.blocks.iter().enumerate() {
+        let start = position + 1;
+
+        for instruction in &block.instructions {
+            position += 1;
+
+            if let Instruction::Call { .. } = instruction {
+                calls.push(position);
+            }
+
+            let (uses, defs) = give_uses_and_defs(function, instruction);
+            for variable in uses.into_iter().chain(defs) {
+                extend(variable, position);
+            }
+        }
+
+        position += 1;
+        for variable in give_temps(&block.terminator.values()) {
+            extend(variable, position);
+        }
+
+        for variable in &liveness[i_block].0 {
+            extend(*variable, start);
+        }
+        for variable in &liveness[i_block].1 {
+            extend(*variable, position);
+        }
+    }
+
+    // Written in the same order for each compilation
+    let variables = (0..function.locals.len())
+        .map(|i| Variable::Local(LocalId(i)))
+        .chain((0..function.temps.len()).map(|i| Variable::Temp(Temp(i))));
+
+    let mut intervals: Vec<Interval> = variables
+        .filter(|variable| match variable {
+            Variable::Temp(temp) => !function.temps[temp.0].is_float(),
+            Variable::Local(_) => true,
+        })
+        .filter_map(|variable| {
+            let (start, end) = *ranges.get(&variable)?;
+
+            // The call's result is written after the call
+            let is_crossing_call = calls.iter().any(|call| start < *call && *call <= end);
+            let is_parameter = match variable {
+                Variable::Local(local) => function.parameters.contains(&local),
+                Variable::Temp(_) => false,
+            };
+
+            Some(Interval {
+                variable,
+                start,
+                end,
+                needs_callee_saved: is_crossing_call || is_parameter,
+            })
+        })
+        .collect();
+
+    intervals.sort_by_key(|interval| interval.start);
+    intervals
+}
+
+/// Gives the variables live at the start and at the end of each block
+fn give_liveness(function: &Function) -> Vec<(HashSet<Variable>, HashSet<Variable>)> {
+    // Variables used before being defined in the block, and defined in the
+    // block
+    let uses_and_defs: Vec<(HashSet<Variable>, HashSet<Variable>)> = function.blocks
+        .iter()
+        .map(|block| give_block_uses_and_defs(function, block))
+        .collect();
+
+    let i_blocks: Dict<_, usize> = function.blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| (block.id, i))
+        .collect();
+
+    let mut liveness: Vec<(HashSet<Variable>, HashSet<Variable>)> =
+        vec![(HashSet::new(), HashSet::new()); function.blocks.len()];
+
+    loop {
+        let mut is_changed = false;
+
+        for (i_block, block) in function.blocks.iter().enumerate().rev() {
+            let live_out: HashSet<Variable> = block.terminator
+                .successors()
+                .iter()
+                .filter_map(|successor| i_blocks.get(successor))
+                .flat_map(|i_successor| liveness[*i_successor].0.clone())
+                .collect();
+
+            let (uses, defs) = &uses_and_defs[i_block];
+            let live_in: HashSet<Variable> = uses
+                .iter()
+                .chain(live_out.difference(defs))
+                .copied()
+                .collect();
+
+            if live_in != liveness[i_block].0 || live_out != liveness[i_block].1 {
+                liveness[i_block] = (live_in, live_out);
+                is_changed = true;
+            }
+        }
+
+        if !is_changed {
+            break;
+        }
+    }
+
+    liveness
+}
+
+fn give_block_uses_and_defs(function: &Function, block: &Block) -> (HashSet<Variable>, HashSet<Variable>) {
+    let mut block_uses: HashSet<Variable> = HashSet::new();
+    let mut block_defs: HashSet<Variable> = HashSet::new();
+
+    for instruction in &block.instructions {
+        let (uses, defs) = give_uses_and_defs(function, instruction);
+
+        for variable in uses {
+            if !block_defs.contains(&variable) {
+                block_uses.insert(variable);
+            }
+        }
+        block_defs.extend(defs);
+    }
+
+    for variable in give_temps(&block.terminator.values()) {
+        if !block_defs.contains(&variable) {
+            block_uses.insert(variable);
+        }
+    }
+
+    (block_uses, block_defs)
+}
+
+/// Gives the variables read then the variables written by the instruction,
+/// only the ones which can be in a register
+fn give_uses_and_defs(function: &Function, instruction: &Instruction) -> (Vec<Variable>, Vec<Variable>) {
+    let mut uses = give_temps(&instruction.values());
+    let mut defs: Vec<Variable> = instruction.dest()
+        .map(Variable::Temp)
+        .into_iter()
+        .collect();
+
+    match instruction {
+        Instruction::Load { local, .. } if is_in_register(function, *local) => {
+            uses.push(Variable::Local(*local));
+        }
+        Instruction::Store { local, .. } if is_in_register(function, *local) => {
+            defs.push(Variable::Local(*local));
+        }
+        _ => {}
+    }
+
+    (uses, defs)
+}
+
+fn give_temps(values: &[Value]) -> Vec<Variable> {
+    values.iter()
+        .filter_map(|value| match value {
+            Value::Temp(temp) => Some(Variable::Temp(*temp)),
+            Value::Constant(_) => None,
+        })
+        .collect()
+}
+
+/// An array is always in the stack, its elements are next to each other. A 
+/// floating-point value too, it's computed in the vector registers
+fn is_in_register(function: &Function, local: LocalId) -> bool {
+    let type_ = &function.locals[local.0].type_;
+    !matches!(type_, IrType::Array(..)) && !type_.is_float()
+}
+
+#[test]
+fn linear_scan() {
+    use crate::ir::{BinaryOperator, BlockId, Callee, Terminator};
+
+    // t0 = 1 ; t1 = call f() ; t2 = t0 + t1 ; ret t2
+    let function = Function {
+        id: "main".to_string(),
+        symbol: "main".to_string(),
+        parameters: vec![],
+        return_type: IrType::Integer,
+        locals: vec![],
+        temps: vec![IrType::Integer, IrType::BigInteger, IrType::Integer],
+        blocks: vec![
+            Block {
+                id: BlockId(0),
+                instructions: vec![
+                    Instruction::Copy { dest: Temp(0), value: Value::Constant(1) },
+                    Instruction::Call {
+                        dest: Temp(1),
+                        callee: Callee { symbol: "f".to_string(), is_extern: true },
+                        arguments: vec![],
+                    },
+                    Instruction::Binary {
+                        dest: Temp(2),
+                        operator: BinaryOperator::Add,
+                        left: Value::Temp(Temp(0)),
+                        right: Value::Temp(Temp(1)),
+                    },
+                ],
+                terminator: Terminator::Return(Some(Value::Temp(Temp(2)))),
+            },
+        ],
+    };
+
+    let registers = Registers {
+        callee_saved: vec!["rbx", "r12"],
+        caller_saved: vec!["r10"],
+    };
+
+    // "t0" lives across the call, "t2" is computed while "r10" still holds
+    // "t1" : both get a callee-saved register
+    assert_eq!(allocate(&function, &registers), Allocation {
+        temps: vec![
+            Location::Register("rbx"),
+            Location::Register("r10"),
+            Location::Register("r12"),
+        ],
+        locals: vec![],
+        saved_registers: vec!["rbx", "r12"],
+    });
+}
@@ -2,26 +2,31 @@
 // Under the MIT License
 // Copyright (c) Junon, Antonin Hérault
 
+pub mod cache;
 pub mod compilers;
 pub mod data;
 pub mod defaults;
+pub mod dump;
+pub mod ir;
 pub mod scope;
 
-use std::collections::HashMap as Dict;
-
-use jup::lang::{
-    tokens::Token,
-    elements::Element,
+use std::{
+    collections::HashMap as Dict,
+    env,
+    thread,
 };
 
-use x64asm::formatter::Formatter;
 use args::Args;
 use rslog::{
     level::LogLevel, 
     log::Log, 
     logger::Logger
 };
-use platform::Platform;
+use platform::target::{
+    Arch,
+    Os,
+    Target,
+};
 use strings::manager::StringsManager;
 
 use crate::{
@@ -35,10 +40,10 @@ use crate::{
         CompilerCodeData,
         CompilerStacksData,
     },
-    scope::Scope,
+    dump::Dump,
 };
 
-/// Runs the right compiler according to the platform and set some important
+/// Runs the right compiler according to the target and set some important
 /// parameters as a `CompilerData` object sent to the platform's compiler
 pub fn run_compiler(sources: &Vec<String>, options: &Dict<String, String>, sm: &StringsManager) {
     let mut logger = Logger::new();
@@ -50,85 +55,138 @@ pub fn run_compiler(sources: &Vec<String>, options: &Dict<String, String>, sm: &
         logger.add_log(Log::info(sm.get().logs.infos.library_building.title.as_ref().unwrap().get(sm)));
     });
 
-    // Retrieves the platform from `Args`
-    let mut platform: Platform = platform::get_current();
-    Args::when_flag('p', options, |mut platform_id: String| {
-        platform_id = platform_id.to_lowercase();
-        platform = platform::get_from_id(platform_id)
+    let mut is_archive: bool = false;
+    Args::when_long_flag("archive", options, |_| {
+        is_archive = true;
+        logger.add_log(Log::info(sm.get().logs.infos.static_library_building.title.as_ref().unwrap().get(sm)));
+    });
+
+    // Retrieves the C library linking mode from `Args`
+    let mut is_libc: bool = false;
+    Args::when_long_flag("libc", options, |_| {
+        is_libc = true;
+        logger.add_log(Log::info(sm.get().logs.infos.libc_linking.title.as_ref().unwrap().get(sm)));
+    });
+
+    // Retrieves from `Args` if the divisions check their divisor
+    let mut is_division_checked: bool = false;
+    Args::when_long_flag("check-division", options, |_| is_division_checked = true);
+
+    // Retrieves from `Args` if the optional optimizations are done
+    let mut is_optimized: bool = false;
+    Args::when_flag('O', options, |_| is_optimized = true);
+
+    // Retrieves from `Args` if the build cache has to be ignored
+    let mut is_forced: bool = false;
+    Args::when_long_flag("force", options, |_| is_forced = true);
+
+    // Retrieves the number of jobs from `Args`, by default one job for each 
+    // available processor
+    let mut jobs: usize = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    Args::when_flag('j', options, |n_jobs: String| {
+        match n_jobs.parse::<usize>() {
+            Ok(n_jobs) if n_jobs > 0 => jobs = n_jobs,
+            _ => {
+                logger.add_log(
+                    Log::new(
+                        LogLevel::Error,
+                        sm.get().logs.errors.invalid_jobs.title.as_ref().unwrap().get(sm),
+                        sm.get().logs.errors.invalid_jobs.message.as_ref().unwrap().get(sm)
+                            .replacen("{}", &n_jobs, 1)
+                    )
+                );
+            }
+        }
+    });
+
+    // Retrieves the debug dumps from `Args`
+    let mut dumps: Vec<Dump> = vec![];
+    Args::when_long_flag("dump", options, |request: String| {
+        match dump::from_request(&request) {
+            Ok(requested) => dumps = requested,
+            Err(id) => {
+                logger.add_log(
+                    Log::new(
+                        LogLevel::Error,
+                        sm.get().logs.errors.invalid_dump_request.title.as_ref().unwrap().get(sm),
+                        sm.get().logs.errors.invalid_dump_request.message.as_ref().unwrap().get(sm)
+                            .replacen("{}", &id, 1)
+                    )
+                    .add_hint(sm.get().logs.errors.invalid_dump_request.hint.as_ref().unwrap().get(sm)
+                        .replacen("{}", &dump::DUMPS.join(", "), 1)
+                    ),
+                );
+            }
+        }
+    });
+
+    // Retrieves the target from `Args`, the current one is the default
+    let mut target_triple: String = match platform::get_current() {
+        Some(target) => target.triple(),
+        None => format!("{}-{}", env::consts::ARCH, env::consts::OS),
+    };
+    Args::when_long_flag("target", options, |triple: String| {
+        target_triple = triple;
     });
 
-    // Tells the current platform. It can be wrong (checked above)
+    // Tells the current target. It can be wrong (checked below)
     logger.add_log(Log::info(
-        sm.get().logs.infos.platform.title.as_ref().unwrap().get(sm)
-            .replacen("{}", format!("{:?}", platform).as_str(), 1)
+        sm.get().logs.infos.target.title.as_ref().unwrap().get(sm)
+            .replacen("{}", &target_triple, 1)
     ));
 
-    // Platform checking for wrong not compatible platforms
-    match platform.clone() {
-        Platform::Unknown(invalid_platform_id) => {
-            logger.add_log(
-                Log::new(
-                    LogLevel::Error,
-                    sm.get().logs.errors.platform.title.as_ref().unwrap().get(sm),
-                    sm.get().logs.errors.platform.message.as_ref().unwrap().get(sm)
-                        .replacen("{}", &invalid_platform_id, 1)
-                )
-                .add_hint(sm.get().logs.errors.platform.hint.as_ref().unwrap().get(sm)
-                    .replacen("{}", platform::AVAILABLE_PLATFORMS, 1)
-                ),
-            );
-        }
-        _ => {} // valid platform
+    // Target checking for invalid or not supported targets
+    let target: Option<Target> = Target::from_triple(&target_triple)
+        .filter(|target| target.is_supported());
+
+    if target.is_none() {
+        logger.add_log(
+            Log::new(
+                LogLevel::Error,
+                sm.get().logs.errors.target.title.as_ref().unwrap().get(sm),
+                sm.get().logs.errors.target.message.as_ref().unwrap().get(sm)
+                    .replacen("{}", &target_triple, 1)
+            )
+            .add_hint(sm.get().logs.errors.target.hint.as_ref().unwrap().get(sm)
+                .replacen("{}", &platform::SUPPORTED_TARGETS.join(", "), 1)
+            ),
+        );
     }
     logger.interpret();
 
+    // Already checked just above
+    let target: Target = target.unwrap();
+
     // Sets important information for the compiler
     let data = CompilerData {
         is_library,
+        is_archive,
+        is_libc,
+        is_division_checked,
+        is_optimized,
+        is_forced,
+        target,
+        jobs,
+        dumps,
         sources: sources.clone(),
+        signatures: Dict::new(),
         options: options.clone(),
     };
 
-    let tools = CompilerTools {
-        strings_manager: sm.clone(),
-        asm_formatter: Formatter::new(false),
-    };
-
-    let code_data = CompilerCodeData {
-        current_source: String::new(),
-        current_parsed: vec![],
-        scope: Scope::new(),
-
-        next_element: Element::Other(Token::None),
-        is_skip_next: false,
-        
-        is_condition: false,
-        n_condition: 0,
-
-        is_loop: false,
-        n_loop: 0,
-
-        previous_operator: Token::None,
-    };
-
-    let stacks_data = CompilerStacksData {
-        variable_stack: Dict::new(),
-        i_variable_stack: 0,
-
-        i_parameter_stack: 0,
-    };
+    let tools = CompilerTools::new(sm.clone());
+    let code_data = CompilerCodeData::new();
+    let stacks_data = CompilerStacksData::new();
 
     let all_data = (data, tools, code_data, stacks_data);
 
-    // Runs the right compiler according to the platform
-    match platform {
-        Platform::Android => todo!(),
-        Platform::IOS => todo!(),
-        Platform::Linux => LinuxCompiler::new(all_data).run(),
-        Platform::MacOS => todo!(),
-        Platform::Windows => todo!(),
+    // Runs the right compiler according to the target
+    match (target.arch, target.os) {
+        (Arch::X86_64, Os::Linux) => LinuxCompiler::new(all_data).run(),
 
-        // Already checked previously in this own function
-        Platform::Unknown(_platform) => panic!(), // never happens
+        // Already checked previously in this own function with
+        // `Target::is_supported()`
+        _ => panic!(), // never happens
     }
 }
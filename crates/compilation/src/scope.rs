@@ -2,7 +2,10 @@
 // Under the MIT License
 // Copyright (c) Junon, Antonin Hérault
 
-use std::string::ToString;
+use std::{
+    path::{Component, Path},
+    string::ToString,
+};
 
 use crate::defaults;
 
@@ -38,6 +41,35 @@ impl Scope {
         Self { scope: start }
     }
 
+    /// Creates the scope of a module from its source file's path, relative to
+    /// the current directory : "src/math.ju" is the module "src.math".
+    ///
+    /// The characters that cannot be written in an Assembly label are replaced
+    /// by '_', and '_' is added before a name starting with a digit
+    pub fn from_path(source_path: &str) -> Self {
+        let path = Path::new(source_path).with_extension("");
+
+        let scope = path.components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => {
+                    let mut name: String = name.to_string_lossy()
+                        .chars()
+                        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+                        .collect();
+
+                    if name.starts_with(|c: char| c.is_ascii_digit()) {
+                        name.insert(0, '_');
+                    }
+
+                    Some(name)
+                }
+                _ => None,
+            })
+            .collect();
+
+        Self { scope }
+    }
+
     pub fn push(&mut self, sub_scope: String) {
         self.scope.push(sub_scope);
     }
@@ -50,3 +82,11 @@ impl Scope {
         self.scope = vec![];
     }
 }
+
+#[test]
+fn scope_from_path() {
+    assert_eq!(Scope::from_path("math.ju").to_string(), "math");
+    assert_eq!(Scope::from_path("./src/math.ju").to_string(), "src.math");
+    assert_eq!(Scope::from_path("../lib/my-lib.ju").to_string(), "lib.my_lib");
+    assert_eq!(Scope::from_path("2d/shapes.ju").to_string(), "_2d.shapes");
+}
@@ -0,0 +1,270 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+//! Writer for static libraries, in the GNU "ar" format with a symbol index, so
+//! no external "ar" program is required.
+//!
+//! An archive is written like that :
+//! ```text
+//! !<arch>\n
+//! <header> "/"          symbol index : count, members offsets, symbols names
+//! <header> "//"         long members names (only when a name is too long)
+//! <header> "main.ju.o/" member content
+//! ...
+//! ```
+
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+const MAGIC: &[u8] = b"!<arch>\n";
+const HEADER_SIZE: usize = 60;
+/// Longest member name that can be written in a header, with its '/' after
+const MAX_SHORT_NAME: usize = 15;
+
+/// Creates the static library `archive_path` containing all the object files
+/// `members`.
+///
+/// The global symbols defined by the ELF members are written in the symbol
+/// index, so the linker knows which member to retrieve for a symbol
+pub fn create(archive_path: &Path, members: &[PathBuf]) -> io::Result<()> {
+    let mut contents: Vec<(String, Vec<u8>)> = vec![];
+    for member in members {
+        let name = member.file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        contents.push((name, fs::read(member)?));
+    }
+
+    fs::write(archive_path, build(&contents)?)
+}
+
+/// Builds the archive's content from the members names and contents.
+///
+/// Returns an error when a member is a malformed ELF object file
+pub fn build(members: &[(String, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    // Long names table, and the name to write in each member's header
+    let mut long_names = String::new();
+    let mut header_names: Vec<String> = vec![];
+
+    for (name, _content) in members {
+        if name.len() > MAX_SHORT_NAME {
+            header_names.push(format!("/{}", long_names.len()));
+            long_names += &format!("{}/\n", name);
+        } else {
+            header_names.push(format!("{}/", name));
+        }
+    }
+
+    // Symbols defined by each member
+    let mut symbols: Vec<Vec<String>> = vec![];
+    for (name, content) in members {
+        symbols.push(elf_global_symbols(content).map_err(|error| {
+            io::Error::new(error.kind(), format!("{} : {}", name, error))
+        })?);
+    }
+    let n_symbols: usize = symbols.iter().map(|names| names.len()).sum();
+
+    let symbol_index_size = 4
+        + 4 * n_symbols
+        + symbols.iter().flatten().map(|name| name.len() + 1).sum::<usize>();
+
+    // Computes where each member header will be written
+    let mut offset = MAGIC.len() + HEADER_SIZE + padded(symbol_index_size);
+    if !long_names.is_empty() {
+        offset += HEADER_SIZE + padded(long_names.len());
+    }
+
+    let mut member_offsets: Vec<usize> = vec![];
+    for (_name, content) in members {
+        member_offsets.push(offset);
+        offset += HEADER_SIZE + padded(content.len());
+    }
+
+    // Writes the archive
+    let mut archive: Vec<u8> = MAGIC.to_vec();
+
+    let mut symbol_index: Vec<u8> = vec![];
+    symbol_index.extend_from_slice(&(n_symbols as u32).to_be_bytes());
+    for (i, names) in symbols.iter().enumerate() {
+        for _name in names {
+            symbol_index.extend_from_slice(&(member_offsets[i] as u32).to_be_bytes());
+        }
+    }
+    for name in symbols.iter().flatten() {
+        symbol_index.extend_from_slice(name.as_bytes());
+        symbol_index.push(0);
+    }
+    write_member(&mut archive, "/", &symbol_index);
+
+    if !long_names.is_empty() {
+        write_member(&mut archive, "//", long_names.as_bytes());
+    }
+
+    for (i, (_name, content)) in members.iter().enumerate() {
+        write_member(&mut archive, &header_names[i], content);
+    }
+
+    Ok(archive)
+}
+
+/// Members are aligned on two bytes
+fn padded(size: usize) -> usize {
+    size + size % 2
+}
+
+/// Writes the header then the content, the dates and owners are zero for
+/// reproducible archives
+fn write_member(archive: &mut Vec<u8>, header_name: &str, content: &[u8]) {
+    let header = format!(
+        "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+        header_name,
+        0,
+        0,
+        0,
+        644,
+        content.len()
+    );
+
+    archive.extend_from_slice(header.as_bytes());
+    archive.extend_from_slice(content);
+
+    if content.len() % 2 == 1 {
+        archive.push(b'\n');
+    }
+}
+
+/// Gives the names of the global and weak symbols defined in an ELF64 little
+/// endian object file.
+///
+/// Returns no symbols when the content is not such an object file, and an 
+/// error when an offset or a size of the object file goes outside of it
+fn elf_global_symbols(content: &[u8]) -> io::Result<Vec<String>> {
+    const SHT_SYMTAB: usize = 2;
+    const STB_GLOBAL: u8 = 1;
+    const STB_WEAK: u8 = 2;
+    const SHN_UNDEF: usize = 0;
+    const SECTION_HEADER_SIZE: usize = 64;
+    const SYMBOL_SIZE: usize = 24;
+
+    let is_elf64_le = content.len() > 0x40
+        && &content[0..4] == b"\x7fELF"
+        && content[4] == 2 // 64 bits
+        && content[5] == 1; // little endian
+
+    if !is_elf64_le {
+        return Ok(vec![]);
+    }
+
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "malformed ELF object file");
+
+    // Gives `size` bytes of the object file at the offset `at`
+    let read = |at: usize, size: usize| {
+        at.checked_add(size)
+            .and_then(|end| content.get(at..end))
+            .ok_or_else(malformed)
+    };
+
+    // Numbers in bytes already read
+    let read_u16 = |bytes: &[u8], at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]) as usize;
+    let read_u32 = |bytes: &[u8], at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+    let read_u64 = |bytes: &[u8], at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize;
+
+    let section_headers = read_u64(content, 0x28);
+    let section_header_size = read_u16(content, 0x3a);
+    let n_sections = read_u16(content, 0x3c);
+
+    // Type, offset, size and linked section of a section, from its index
+    let section = |i: usize| -> io::Result<(usize, usize, usize, usize)> {
+        let position = i.checked_mul(section_header_size)
+            .and_then(|position| position.checked_add(section_headers))
+            .ok_or_else(malformed)?;
+        let header = read(position, SECTION_HEADER_SIZE)?;
+
+        Ok((
+            read_u32(header, 0x04),
+            read_u64(header, 0x18),
+            read_u64(header, 0x20),
+            read_u32(header, 0x28),
+        ))
+    };
+
+    let mut names: Vec<String> = vec![];
+
+    for i in 0..n_sections {
+        let (type_, offset, size, link) = section(i)?;
+        if type_ != SHT_SYMTAB {
+            continue;
+        }
+
+        // The names of the symbols are stored in the linked string table
+        let (_type, strings_offset, _size, _link) = section(link)?;
+        let strings = content.get(strings_offset..).ok_or_else(malformed)?;
+
+        for symbol in read(offset, size)?.chunks(SYMBOL_SIZE) {
+            if symbol.len() != SYMBOL_SIZE {
+                return Err(malformed());
+            }
+
+            let name_offset = read_u32(symbol, 0);
+            let bind = symbol[4] >> 4;
+            let section_index = read_u16(symbol, 6);
+
+            if (bind != STB_GLOBAL && bind != STB_WEAK) || section_index == SHN_UNDEF {
+                continue;
+            }
+
+            let name = strings.get(name_offset..).ok_or_else(malformed)?;
+            let name_length = name.iter()
+                .position(|byte| *byte == 0)
+                .ok_or_else(malformed)?;
+
+            names.push(String::from_utf8_lossy(&name[..name_length]).to_string());
+        }
+    }
+
+    Ok(names)
+}
+
+#[test]
+fn archive_layout() {
+    let archive = build(&[
+        ("a.o".to_string(), b"abc".to_vec()),
+        ("a_very_long_object_name.ju.o".to_string(), b"abcd".to_vec()),
+    ]).unwrap();
+
+    assert!(archive.starts_with(MAGIC));
+
+    // Symbol index with no symbols, only the count is written
+    let header = String::from_utf8_lossy(&archive[8..8 + HEADER_SIZE]).to_string();
+    assert!(header.starts_with("/ "));
+    assert!(header.ends_with("4         `\n"));
+    assert_eq!(&archive[68..72], &[0, 0, 0, 0]);
+
+    // Long names table
+    let header = String::from_utf8_lossy(&archive[72..72 + HEADER_SIZE]).to_string();
+    assert!(header.starts_with("// "));
+    assert_eq!(&archive[132..162], b"a_very_long_object_name.ju.o/\n");
+
+    // Members, the odd sized one is padded
+    let header = String::from_utf8_lossy(&archive[162..162 + HEADER_SIZE]).to_string();
+    assert!(header.starts_with("a.o/ "));
+    assert_eq!(&archive[222..226], b"abc\n");
+
+    let header = String::from_utf8_lossy(&archive[226..226 + HEADER_SIZE]).to_string();
+    assert!(header.starts_with("/0 "));
+    assert_eq!(&archive[286..], b"abcd");
+
+    // An ELF object file whose section headers are outside of it
+    let mut truncated = b"\x7fELF\x02\x01".to_vec();
+    truncated.resize(0x41, 0);
+    truncated[0x28] = 0xff;
+    truncated[0x3c] = 1;
+    assert!(build(&[("truncated.o".to_string(), truncated)]).is_err());
+}
@@ -2,8 +2,14 @@
 // Under the MIT License
 // Copyright (c) Junon, Antonin Hérault
 
-use std::env;
-use std::process::Command;
+pub mod archive;
+pub mod target;
+
+use std::{
+    env,
+    path::PathBuf,
+    process::Command,
+};
 
 use rslog::{
     level::LogLevel, 
@@ -11,48 +17,11 @@ use rslog::{
     logger::Logger
 };
 
-/// Constants where all available platforms are stored, "linked" with `Platform`
-///
-/// Should be updated in the same of `Platform` updates
-pub const AVAILABLE_PLATFORMS: &str = "Android, IOS, Linux, MacOS, Windows";
-
-/// All available platform where code can be compiled.
-///
-/// Also can store an unknown platform if any right platform was retrieved
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub enum Platform {
-    Android,
-    IOS,
-    Linux,
-    MacOS,
-    Windows,
-    /// Unknown retrieved platform, will stop the program when matched with
-    Unknown(String),
-}
-
-/// Gets the platform where the compiler is currently running.
-/// 
-/// See constants at https://doc.rust-lang.org/std/env/consts/constant.OS.html
-pub fn get_current() -> Platform {
-    // The platform identifier is set as lowercase according to the
-    // documentation page (See : This function's documentation)
-    let platform_id: &str = env::consts::OS;
-    get_from_id(platform_id.to_string())
-}
-
-/// Gets the platform as a `Platform` enum object from an identifier.
-///
-/// The identifier should be as lowercase
-pub fn get_from_id(platform_id: String) -> Platform {
-    match platform_id.as_str() {
-        "android" => Platform::Android,
-        "ios" => Platform::IOS,
-        "linux" => Platform::Linux,
-        "macos" => Platform::MacOS,
-        "windows" => Platform::Windows,
-        _ => Platform::Unknown(platform_id),
-    }
-}
+pub use target::{
+    get_current,
+    Target,
+    SUPPORTED_TARGETS,
+};
 
 /// Way to call a program on the system
 ///
@@ -93,3 +62,34 @@ pub fn exec(program_id: String, arguments: &[String]) {
         logger.interpret();
     }
 }
+
+/// Way to call a program on the system and retrieve its output, nothing is
+/// logged
+///
+/// Returns the standard output when the program succeeded, otherwise the error
+/// output (or why it cannot be executed)
+pub fn exec_output(program_id: &str, arguments: &[String]) -> Result<String, String> {
+    let output = Command::new(program_id)
+        .args(arguments)
+        .output()
+        .map_err(|error| format!("'{}' : {}", program_id, error))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!(
+            "'{}' : {}", 
+            program_id, 
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Searches a program in the directories of the "PATH" environment variable
+pub fn find_program(program_id: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+
+    env::split_paths(&paths)
+        .map(|directory| directory.join(program_id))
+        .find(|path| path.is_file())
+}
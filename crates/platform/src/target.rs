@@ -0,0 +1,257 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+use std::env;
+
+/// All target triples the compiler is able to produce code for.
+///
+/// A triple is written as "<arch>-<os>-<env>", like "x86_64-linux-gnu"
+pub const SUPPORTED_TARGETS: [&str; 2] = [
+    "x86_64-linux-gnu",
+    "x86_64-linux-musl",
+];
+
+/// Processor architecture of a target
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Arch {
+    X86_64,
+    Aarch64,
+}
+
+/// Operating system of a target
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Os {
+    Android,
+    IOS,
+    Linux,
+    MacOS,
+    Windows,
+}
+
+/// Environment (C library / ABI flavour) of a target
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Env {
+    Gnu,
+    Musl,
+    Msvc,
+    /// No specific environment, like for Apple platforms
+    None,
+}
+
+/// How the parameters and the return values are passed between functions
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum CallingConvention {
+    SystemV,
+    Win64,
+    Aapcs64,
+}
+
+/// Format of the object files produced by the assembler
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ObjectFormat {
+    Elf64,
+    MachO64,
+    Win64,
+}
+
+/// Target where the code will be compiled for, made of an architecture, an
+/// operating system and an environment.
+///
+/// Replaces the old OS-only platform: everything depending on the target
+/// (calling convention, object format, ...) is retrieved from it
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Target {
+    pub arch: Arch,
+    pub os: Os,
+    pub env: Env,
+}
+
+impl Target {
+    pub fn new(arch: Arch, os: Os, env: Env) -> Self {
+        Self { arch, os, env }
+    }
+
+    /// Parses a target triple like "x86_64-linux-gnu".
+    ///
+    /// The environment can be omitted, then the default one for the operating
+    /// system is used ("x86_64-linux" is "x86_64-linux-gnu").
+    ///
+    /// Returns `None` when a component is unknown, it does not check if the
+    /// target is supported (See : `Target::is_supported()`)
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        let triple = triple.to_lowercase();
+        let components: Vec<&str> = triple.split('-').collect();
+
+        let (arch, os, env) = match components.as_slice() {
+            [arch, os] => (*arch, *os, None),
+            [arch, os, env] => (*arch, *os, Some(*env)),
+            _ => return None,
+        };
+
+        let arch = arch_from_id(arch)?;
+        let os = os_from_id(os)?;
+        let env = match env {
+            Some(env) => env_from_id(env)?,
+            None => default_env(os),
+        };
+
+        Some(Self::new(arch, os, env))
+    }
+
+    /// Gives the target as a triple, the opposite of `Target::from_triple()`
+    pub fn triple(&self) -> String {
+        let arch = match self.arch {
+            Arch::X86_64 => "x86_64",
+            Arch::Aarch64 => "aarch64",
+        };
+
+        let os = match self.os {
+            Os::Android => "android",
+            Os::IOS => "ios",
+            Os::Linux => "linux",
+            Os::MacOS => "macos",
+            Os::Windows => "windows",
+        };
+
+        match self.env {
+            Env::Gnu => format!("{}-{}-gnu", arch, os),
+            Env::Musl => format!("{}-{}-musl", arch, os),
+            Env::Msvc => format!("{}-{}-msvc", arch, os),
+            Env::None => format!("{}-{}", arch, os),
+        }
+    }
+
+    /// Is the target listed in `SUPPORTED_TARGETS` ?
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_TARGETS.contains(&self.triple().as_str())
+    }
+
+    /// Size of a pointer in bits
+    pub fn pointer_width(&self) -> usize {
+        match self.arch {
+            Arch::X86_64 | Arch::Aarch64 => 64,
+        }
+    }
+
+    pub fn calling_convention(&self) -> CallingConvention {
+        match (self.arch, self.os) {
+            (Arch::Aarch64, _) => CallingConvention::Aapcs64,
+            (Arch::X86_64, Os::Windows) => CallingConvention::Win64,
+            (Arch::X86_64, _) => CallingConvention::SystemV,
+        }
+    }
+
+    pub fn object_format(&self) -> ObjectFormat {
+        match self.os {
+            Os::Android | Os::Linux => ObjectFormat::Elf64,
+            Os::IOS | Os::MacOS => ObjectFormat::MachO64,
+            Os::Windows => ObjectFormat::Win64,
+        }
+    }
+
+    /// Flags to give to the assembler to produce the right object file
+    pub fn assembler_flags(&self) -> Vec<String> {
+        let format = match self.object_format() {
+            ObjectFormat::Elf64 => "-felf64",
+            ObjectFormat::MachO64 => "-fmacho64",
+            ObjectFormat::Win64 => "-fwin64",
+        };
+
+        vec![format.to_string()]
+    }
+
+    /// Prefix added before each global symbol by the object format
+    pub fn symbol_prefix(&self) -> &'static str {
+        match self.object_format() {
+            ObjectFormat::MachO64 => "_",
+            ObjectFormat::Elf64 | ObjectFormat::Win64 => "",
+        }
+    }
+
+    /// Path of the program loading the shared libraries when a dynamically
+    /// linked binary is started, if the target has one
+    pub fn dynamic_linker(&self) -> Option<&'static str> {
+        match (self.arch, self.os, self.env) {
+            (Arch::X86_64, Os::Linux, Env::Gnu) => Some("/lib64/ld-linux-x86-64.so.2"),
+            (Arch::X86_64, Os::Linux, Env::Musl) => Some("/lib/ld-musl-x86_64.so.1"),
+            (Arch::Aarch64, Os::Linux, Env::Gnu) => Some("/lib/ld-linux-aarch64.so.1"),
+            (Arch::Aarch64, Os::Linux, Env::Musl) => Some("/lib/ld-musl-aarch64.so.1"),
+            _ => None,
+        }
+    }
+}
+
+/// The identifiers are the ones from `std::env::consts::ARCH`
+fn arch_from_id(arch_id: &str) -> Option<Arch> {
+    match arch_id {
+        "x86_64" | "amd64" | "x64" => Some(Arch::X86_64),
+        "aarch64" | "arm64" => Some(Arch::Aarch64),
+        _ => None,
+    }
+}
+
+/// The identifiers are the ones from `std::env::consts::OS`
+fn os_from_id(os_id: &str) -> Option<Os> {
+    match os_id {
+        "android" => Some(Os::Android),
+        "ios" => Some(Os::IOS),
+        "linux" => Some(Os::Linux),
+        "macos" | "darwin" => Some(Os::MacOS),
+        "windows" => Some(Os::Windows),
+        _ => None,
+    }
+}
+
+fn env_from_id(env_id: &str) -> Option<Env> {
+    match env_id {
+        "gnu" => Some(Env::Gnu),
+        "musl" => Some(Env::Musl),
+        "msvc" => Some(Env::Msvc),
+        _ => None,
+    }
+}
+
+fn default_env(os: Os) -> Env {
+    match os {
+        Os::Linux => Env::Gnu,
+        Os::Windows => Env::Msvc,
+        Os::Android | Os::IOS | Os::MacOS => Env::None,
+    }
+}
+
+/// Gets the target where the compiler is currently running.
+///
+/// See constants at https://doc.rust-lang.org/std/env/consts/index.html
+///
+/// Returns `None` when the architecture or the operating system is not known
+/// by the compiler
+pub fn get_current() -> Option<Target> {
+    let arch = arch_from_id(env::consts::ARCH)?;
+    let os = os_from_id(env::consts::OS)?;
+
+    let env = if cfg!(target_env = "musl") {
+        Env::Musl
+    } else {
+        default_env(os)
+    };
+
+    Some(Target::new(arch, os, env))
+}
+
+#[test]
+fn target_triples() {
+    for triple in SUPPORTED_TARGETS {
+        let target = Target::from_triple(triple).unwrap();
+        assert_eq!(target.triple(), triple);
+        assert!(target.is_supported());
+    }
+
+    let target = Target::from_triple("x86_64-linux").unwrap();
+    assert_eq!(target.triple(), "x86_64-linux-gnu");
+    assert_eq!(target.object_format(), ObjectFormat::Elf64);
+    assert_eq!(target.calling_convention(), CallingConvention::SystemV);
+
+    assert!(Target::from_triple("x86_64-plan9-gnu").is_none());
+    assert!(!Target::from_triple("aarch64-macos").unwrap().is_supported());
+}
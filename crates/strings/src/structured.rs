@@ -27,13 +27,30 @@ pub struct Errors {
     pub wrong_file_extension: Log,
     pub no_file_extension: Log,
     pub execution_failed: Log,
-    pub platform: Log
+    pub target: Log,
+    pub invalid_jobs: Log,
+    pub archive_creation_failed: Log,
+    pub c_runtime_not_found: Log,
+    pub invalid_print_request: Log,
+    pub invalid_dump_request: Log,
+    pub type_mismatch: Log,
+    pub argument_type_mismatch: Log,
+    pub return_type_mismatch: Log,
+    pub operands_type_mismatch: Log,
+    pub value_out_of_range: Log,
+    pub arguments_count: Log,
+    pub missing_return_value: Log,
+    pub unexpected_return_value: Log,
+    pub no_return_value: Log,
+    pub unknown_type: Log,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Infos {
-    pub platform: Log,
+    pub target: Log,
     pub library_building: Log,
+    pub static_library_building: Log,
+    pub libc_linking: Log,
     pub working_directory: Log,
     pub ignored_option_flag: Log,
     pub finished: Log,
@@ -50,17 +67,26 @@ pub struct HelpAvailableFlags {
     pub title: MultiString,
     pub h: MultiString,
     pub l: MultiString,
-    pub p: MultiString,
     pub o: MultiString,
     pub d: MultiString,
     pub a: MultiString,
     pub s: MultiString,
+    pub j: MultiString,
+    pub target: MultiString,
+    pub print: MultiString,
+    pub archive: MultiString,
+    pub force: MultiString,
+    pub dump: MultiString,
+    pub libc: MultiString,
+    pub check_division: MultiString,
+    pub optimize: MultiString,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct HelpArguments {
     pub sources: MultiString,
     pub options: MultiString,
+    pub doctor: MultiString,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -70,8 +96,28 @@ pub struct Help {
     pub available_flags: HelpAvailableFlags,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct Doctor {
+    pub title: MultiString,
+    pub target: MultiString,
+    pub unsupported_target: MultiString,
+    pub assembler: MultiString,
+    pub linker: MultiString,
+    pub not_found: MultiString,
+    pub test_program: MultiString,
+    pub assembled_and_linked: MultiString,
+    pub failed: MultiString,
+    pub speak_language: MultiString,
+    pub output_directory: MultiString,
+    pub writable: MultiString,
+    pub not_writable: MultiString,
+    pub healthy: MultiString,
+    pub not_healthy: MultiString,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Strings {
     pub help: Help,
+    pub doctor: Doctor,
     pub logs: Logs,
 }
@@ -0,0 +1,200 @@
+// This file is part of "juc"
+// Under the MIT License
+// Copyright (c) Junon, Antonin Hérault
+
+use std::{
+    collections::HashMap as Dict,
+    env,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use args::Args;
+use compilation::defaults::linux_defaults;
+use platform::Target;
+use strings::manager::StringsManager;
+
+/// Command checking the toolchain instead of compiling : `juc doctor`
+pub const COMMAND: &str = "doctor";
+
+/// Program assembled and linked to check that the toolchain works
+const TEST_PROGRAM: &str = "\
+section .text
+global _start
+_start:
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+";
+
+/// Reports the state of everything the compiler needs, then exits with a non
+/// zero code when something is wrong
+///
+/// Called when "doctor" is given instead of the source files
+pub fn run(sm: &StringsManager, options: &Dict<String, String>) {
+    let strings = &sm.get().doctor;
+    let mut is_healthy = true;
+
+    println!("\x1b[1m{}\x1b[0m", strings.title.get(sm));
+
+    // Target selected like when compiling, the current one is the default
+    let mut triple: String = match platform::get_current() {
+        Some(target) => target.triple(),
+        None => format!("{}-{}", env::consts::ARCH, env::consts::OS),
+    };
+    Args::when_long_flag("target", options, |selected: String| {
+        triple = selected;
+    });
+
+    let target: Option<Target> = Target::from_triple(&triple)
+        .filter(|target| target.is_supported());
+
+    match &target {
+        Some(target) => {
+            report(true, strings.target.get(sm).replacen("{}", &target.triple(), 1));
+        }
+        None => {
+            is_healthy = false;
+            report(false, strings.unsupported_target.get(sm)
+                .replacen("{}", &triple, 1)
+                .replacen("{}", &platform::SUPPORTED_TARGETS.join(", "), 1)
+            );
+        }
+    }
+
+    // Assembler and linker
+    for (program_id, version_flag, title) in [
+        (linux_defaults::ASSEMBLER, "-v", &strings.assembler),
+        (linux_defaults::LINKER, "-v", &strings.linker),
+    ] {
+        let found = match platform::find_program(program_id) {
+            Some(path) => {
+                let version = platform::exec_output(program_id, &[version_flag.to_string()])
+                    .unwrap_or_default();
+
+                format!(
+                    "{} ({})",
+                    path.display(),
+                    version.lines().next().unwrap_or_default().trim()
+                )
+            }
+            None => {
+                is_healthy = false;
+                report(false, title.get(sm).replacen(
+                    "{}",
+                    &strings.not_found.get(sm).replacen("{}", program_id, 1),
+                    1
+                ));
+                continue;
+            }
+        };
+
+        report(true, title.get(sm).replacen("{}", &found, 1));
+    }
+
+    // Test program, assembled for the target when it's supported
+    match target.as_ref().map(check_test_program) {
+        Some(Ok(())) => {
+            report(true, strings.test_program.get(sm)
+                .replacen("{}", &strings.assembled_and_linked.get(sm), 1)
+            );
+        }
+        Some(Err(error)) => {
+            is_healthy = false;
+            report(false, strings.test_program.get(sm)
+                .replacen("{}", &strings.failed.get(sm).replacen("{}", &error, 1), 1)
+            );
+        }
+        None => {}
+    }
+
+    // Speak language
+    report(true, strings.speak_language.get(sm).replacen("{}", sm.speak_lang(), 1));
+
+    // Output directory, where the files would be generated with the same
+    // options
+    let mut output_dir = PathBuf::from(".");
+    Args::when_flag('o', options, |output_path: String| {
+        let parent = Path::new(&output_path).parent().unwrap();
+        if !parent.as_os_str().is_empty() {
+            output_dir = parent.to_path_buf();
+        }
+    });
+
+    let is_writable = is_writable(&output_dir);
+    if !is_writable {
+        is_healthy = false;
+    }
+
+    report(is_writable, strings.output_directory.get(sm)
+        .replacen("{}", &output_dir.display().to_string(), 1)
+        .replacen("{}", &if is_writable {
+            strings.writable.get(sm)
+        } else {
+            strings.not_writable.get(sm)
+        }, 1)
+    );
+
+    if is_healthy {
+        println!("\n{}", strings.healthy.get(sm));
+        process::exit(0);
+    } else {
+        println!("\n{}", strings.not_healthy.get(sm));
+        process::exit(1);
+    }
+}
+
+fn report(is_ok: bool, message: String) {
+    if is_ok {
+        println!("  \x1b[32m[ok]\x1b[0m   {}", message);
+    } else {
+        println!("  \x1b[31m[fail]\x1b[0m {}", message);
+    }
+}
+
+/// Assembles `TEST_PROGRAM` with the target's flags and links it in a
+/// temporary directory, removed after
+fn check_test_program(target: &Target) -> Result<(), String> {
+    let directory = env::temp_dir().join(format!("juc-doctor-{}", process::id()));
+    fs::create_dir_all(&directory).map_err(|error| error.to_string())?;
+
+    let path = |extension: &str| {
+        directory.join(format!("test.{}", extension))
+            .display()
+            .to_string()
+    };
+
+    let result = fs::write(path("asm"), TEST_PROGRAM)
+        .map_err(|error| error.to_string())
+        .and_then(|_| {
+            let mut arguments = vec![path("asm")];
+            arguments.append(&mut target.assembler_flags());
+            arguments.append(&mut vec!["-o".to_string(), path("o")]);
+
+            platform::exec_output(linux_defaults::ASSEMBLER, &arguments)
+        })
+        .and_then(|_| platform::exec_output(linux_defaults::LINKER, &[
+            "-o".to_string(),
+            path("out"),
+            path("o"),
+        ]));
+
+    fs::remove_dir_all(&directory).ok();
+    result.map(|_| ())
+}
+
+/// Can files be created in the directory ? When it does not exist yet, checks
+/// the first existing parent directory where it would be created
+fn is_writable(directory: &Path) -> bool {
+    let existing = match directory.ancestors().find(|path| path.is_dir()) {
+        Some(existing) => existing,
+        None => return false,
+    };
+
+    let test_file = existing.join(format!(".juc-doctor-{}", process::id()));
+    let is_writable = fs::write(&test_file, "").is_ok();
+
+    fs::remove_file(&test_file).ok();
+    is_writable
+}
@@ -2,6 +2,8 @@
 // Under the MIT License
 // Copyright (c) Junon, Antonin Hérault
 
+mod doctor;
+
 use std::{
     collections::HashMap as Dict,
     env,
@@ -42,6 +44,7 @@ fn main() {
     });
     
     Args::when_flag('h', options, |_| help(&sm));
+    Args::when_long_flag("print", options, |what: String| print(&sm, &what));
     Args::when_flag('d', options, |path: String| {
         let current_dir = Path::new(&path);
         if !current_dir.is_dir() || !current_dir.exists() {
@@ -62,6 +65,11 @@ fn main() {
             .replacen("{}", &env::current_dir().unwrap().display().to_string(), 1)
     ));
 
+    // "juc doctor" checks the toolchain instead of compiling
+    if sources.first().map(|source| source.as_str()) == Some(doctor::COMMAND) {
+        doctor::run(&sm, options);
+    }
+
     // Check after current directory set
     for source in sources {
         let path = Path::new(source);
@@ -120,20 +128,60 @@ fn main() {
 fn help(sm: &StringsManager) {
     let to_write = [
         sm.get().help.title.get(sm),
-        "juc <?sources> <?options...>\n".to_string(),
+        "juc <?sources> <?options...>".to_string(),
+        format!("juc {}\n", doctor::COMMAND),
         sm.get().help.arguments.sources.get(sm),
         sm.get().help.arguments.options.get(sm),
+        sm.get().help.arguments.doctor.get(sm),
         sm.get().help.available_flags.title.get(sm),
         sm.get().help.available_flags.h.get(sm),
         sm.get().help.available_flags.l.get(sm),
-        sm.get().help.available_flags.p.get(sm),
-        "\t\t(Android, IOS, Linux, MacOS, Windows)\n".to_string(),
         sm.get().help.available_flags.o.get(sm),
         sm.get().help.available_flags.d.get(sm),
         sm.get().help.available_flags.a.get(sm),
         sm.get().help.available_flags.s.get(sm),
+        sm.get().help.available_flags.j.get(sm),
+        sm.get().help.available_flags.target.get(sm),
+        sm.get().help.available_flags.print.get(sm),
+        sm.get().help.available_flags.archive.get(sm),
+        sm.get().help.available_flags.force.get(sm),
+        sm.get().help.available_flags.dump.get(sm),
+        sm.get().help.available_flags.libc.get(sm),
+        sm.get().help.available_flags.check_division.get(sm),
+        sm.get().help.available_flags.optimize.get(sm),
     ].join("\n");
 
     print!("\x1b[1m{}\x1b[0m", to_write);
     process::exit(0);
 }
+
+/// Prints an information about the compiler, then exits
+///
+/// Called when "--print" was found in options
+fn print(sm: &StringsManager, what: &str) {
+    match what {
+        "targets" => {
+            for target in platform::SUPPORTED_TARGETS {
+                println!("{}", target);
+            }
+        }
+        _ => {
+            let mut logger = Logger::new();
+            logger.add_log(
+                Log::new(
+                    LogLevel::Error,
+                    sm.get().logs.errors.invalid_print_request.title.as_ref().unwrap().get(sm),
+                    sm.get().logs.errors.invalid_print_request.message.as_ref().unwrap().get(sm)
+                        .replacen("{}", what, 1)
+                )
+                .add_hint(
+                    sm.get().logs.errors.invalid_print_request.hint.as_ref().unwrap().get(sm)
+                        .replacen("{}", "targets", 1)
+                )
+            );
+            logger.interpret();
+        }
+    }
+
+    process::exit(0);
+}